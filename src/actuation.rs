@@ -0,0 +1,343 @@
+//! time history of the blowing slot amplitude
+//!
+//! deterministic actuators are evaluated from the parameters in the config file, while the
+//! amplitudes chosen at runtime (by the RL controller) are read from csv logs.
+
+use crate::prelude::*;
+
+/// the amplitude of the jet at every step, along with the amplitude at each step that the solver
+/// wrote span averages and probe data
+#[derive(mat5::MatFile)]
+struct ActuationOutput {
+    step: Vec<f64>,
+    time: Vec<f64>,
+    amplitude: Vec<f64>,
+    span_average_step: Vec<f64>,
+    span_average_time: Vec<f64>,
+    span_average_amplitude: Vec<f64>,
+    probe_step: Vec<f64>,
+    probe_time: Vec<f64>,
+    probe_amplitude: Vec<f64>,
+}
+
+/// reconstruct the time history of the jet amplitude for a run and write it to .mat and .csv files
+pub(crate) fn actuation_history(args: cli::ReconstructActuation) -> anyhow::Result<()> {
+    let config_path = args.solver_results.join("input.json");
+    let config = Config::load(&config_path, &args.overrides)
+        .with_context(|| format!("failed to read config at path {}", config_path.display()))?;
+
+    let dt = config.timestep(args.dt)?;
+
+    // (output file name, history) pairs for every series that should be written
+    let mut histories = Vec::new();
+
+    if !args.log.is_empty() {
+        for log in &args.log {
+            histories.push((file_stem(log), ActuationHistory::from_csv(log)?));
+        }
+    } else if let cli::JetActuator::Adaptive {
+        training_output,
+        eval_output,
+        ..
+    } = &config.blowing_bc
+    {
+        for (prefix, directory) in [("training", training_output), ("eval", eval_output)] {
+            let directory = match resolve_rl_directory(&args.solver_results, directory) {
+                Some(directory) => directory,
+                None => {
+                    println!("RL output directory {directory} does not exist, skipping it");
+                    continue;
+                }
+            };
+
+            for log in csv_logs(&directory) {
+                let name = format!("{prefix}_{}", file_stem(&log));
+                histories.push((name, ActuationHistory::from_csv(&log)?));
+            }
+        }
+
+        if histories.is_empty() {
+            anyhow::bail!("no csv actuation logs were found in the RL output directories. Pass them directly with --log");
+        }
+    } else {
+        let steps = (0..=config.steps).collect::<Vec<_>>();
+        histories.push((
+            "actuation".to_string(),
+            ActuationHistory::from_config(&config, dt, &steps)?,
+        ));
+    }
+
+    if !args.output_directory.exists() {
+        fs::create_dir_all(&args.output_directory).with_context(|| {
+            format!(
+                "failed to create output directory {}",
+                args.output_directory.display()
+            )
+        })?;
+    }
+
+    let span_average_steps = output_steps(config.span_average_io_steps, config.steps);
+    let probe_steps = output_steps(config.probe_io_steps, config.steps);
+
+    for (name, history) in histories {
+        println!(
+            "writing actuation history {name} ({} steps)",
+            history.steps.len()
+        );
+
+        history.write_csv(
+            &args.output_directory.join(format!("{name}.csv")),
+            dt,
+            &config,
+        )?;
+
+        let to_float = |steps: &[usize]| steps.iter().map(|step| *step as f64).collect::<Vec<_>>();
+        let to_time = |steps: &[usize]| {
+            steps
+                .iter()
+                .map(|step| *step as f64 * dt)
+                .collect::<Vec<_>>()
+        };
+        let to_amplitude = |steps: &[usize]| {
+            steps
+                .iter()
+                .map(|step| history.amplitude_at(*step))
+                .collect::<Vec<_>>()
+        };
+
+        let output = ActuationOutput {
+            step: to_float(&history.steps),
+            time: to_time(&history.steps),
+            amplitude: history.amplitude.clone(),
+            span_average_step: to_float(&span_average_steps),
+            span_average_time: to_time(&span_average_steps),
+            span_average_amplitude: to_amplitude(&span_average_steps),
+            probe_step: to_float(&probe_steps),
+            probe_time: to_time(&probe_steps),
+            probe_amplitude: to_amplitude(&probe_steps),
+        };
+
+        let mat_path = args.output_directory.join(format!("{name}.mat"));
+        let writer = io::BufWriter::new(
+            fs::File::create(&mat_path)
+                .with_context(|| format!("failed to create {}", mat_path.display()))?,
+        );
+        mat5::MatFile::write_contents(&output, writer)?;
+    }
+
+    Ok(())
+}
+
+/// every step that the solver writes an output at, for outputs written every `io_steps` steps
+pub(crate) fn output_steps(io_steps: usize, total_steps: usize) -> Vec<usize> {
+    if io_steps == 0 {
+        return Vec::new();
+    }
+
+    (io_steps..=total_steps).step_by(io_steps).collect()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "actuation".to_string())
+}
+
+/// the RL output directories in the config are paths inside the container (`/RL_metrics/...`).
+/// If they do not exist on this machine, look for them relative to the solver results.
+fn resolve_rl_directory(solver_results: &Path, directory: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(directory);
+
+    if path.is_dir() {
+        return Some(path);
+    }
+
+    let relative = solver_results.join(path.strip_prefix("/").unwrap_or(&path));
+    relative.is_dir().then_some(relative)
+}
+
+/// every csv file in a directory (recursively), sorted by path
+fn csv_logs(directory: &Path) -> Vec<PathBuf> {
+    let mut logs = walkdir::WalkDir::new(directory)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| path.extension().map(|ext| ext == "csv").unwrap_or(false))
+        .collect::<Vec<_>>();
+
+    logs.sort_unstable();
+    logs
+}
+
+/// amplitude of the blowing slot at every solver step that it is known at
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ActuationHistory {
+    /// solver steps, in increasing order
+    pub(crate) steps: Vec<usize>,
+    /// amplitude of the jet at each step
+    pub(crate) amplitude: Vec<f64>,
+}
+
+impl ActuationHistory {
+    /// evaluate the amplitude of a deterministic actuator (see
+    /// [`cli::JetActuator::deterministic_amplitude`]) at each of the given steps
+    pub(crate) fn from_config(config: &Config, dt: f64, steps: &[usize]) -> anyhow::Result<Self> {
+        let amplitude = steps
+            .iter()
+            .map(|step| {
+                config
+                    .blowing_bc
+                    .deterministic_amplitude(*step as f64 * dt)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "the amplitude of the {:?} actuator is chosen at runtime and cannot be reconstructed from the config. Pass an actuation log instead",
+                    config.blowing_bc
+                )
+            })?;
+
+        Ok(Self {
+            steps: steps.to_vec(),
+            amplitude,
+        })
+    }
+
+    /// read an actuation log from a comma separated file.
+    ///
+    /// The first line must be a header containing (at least) `step` and `amplitude` columns.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub(crate) fn from_csv(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read actuation log {}", path.display()))?;
+
+        Self::parse_csv(&contents)
+            .with_context(|| format!("failed to parse actuation log {}", path.display()))
+    }
+
+    fn parse_csv(contents: &str) -> anyhow::Result<Self> {
+        let mut lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("actuation log is empty"))?;
+        let columns = header
+            .split(',')
+            .map(|column| column.trim().to_lowercase())
+            .collect::<Vec<_>>();
+
+        let column_index = |name: &str| {
+            columns
+                .iter()
+                .position(|column| column == name)
+                .ok_or_else(|| anyhow::anyhow!("header `{header}` is missing a `{name}` column"))
+        };
+        let step_idx = column_index("step")?;
+        let amplitude_idx = column_index("amplitude")?;
+
+        let mut rows = Vec::new();
+
+        for (line_number, line) in lines.enumerate() {
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+
+            let field = |idx: usize| {
+                fields.get(idx).copied().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "row {} has {} columns, expected at least {}",
+                        line_number + 1,
+                        fields.len(),
+                        columns.len()
+                    )
+                })
+            };
+
+            // steps may have been written as floats by python
+            let step: f64 = field(step_idx)?
+                .parse()
+                .with_context(|| format!("invalid step on row {}", line_number + 1))?;
+            let amplitude: f64 = field(amplitude_idx)?
+                .parse()
+                .with_context(|| format!("invalid amplitude on row {}", line_number + 1))?;
+
+            rows.push((step.round() as usize, amplitude));
+        }
+
+        rows.sort_by_key(|(step, _)| *step);
+
+        let (steps, amplitude) = rows.into_iter().unzip();
+
+        Ok(Self { steps, amplitude })
+    }
+
+    /// write the history to a csv file with `step`, `time`, and `amplitude` columns. The
+    /// `span_average` and `probe` columns are 1 at the steps that the solver wrote that output
+    fn write_csv(&self, path: &Path, dt: f64, config: &Config) -> anyhow::Result<()> {
+        let is_output = |io_steps: usize, step: usize| {
+            (io_steps != 0 && step != 0 && step % io_steps == 0) as u8
+        };
+
+        let mut writer = io::BufWriter::new(
+            fs::File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?,
+        );
+
+        writeln!(writer, "step,time,amplitude,span_average,probe")?;
+
+        for (step, amplitude) in self.steps.iter().zip(&self.amplitude) {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                step,
+                *step as f64 * dt,
+                amplitude,
+                is_output(config.span_average_io_steps, *step),
+                is_output(config.probe_io_steps, *step),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// the amplitude at a given step, linearly interpolated between the recorded steps. Steps
+    /// outside of the recorded range take the value at the nearest end.
+    pub(crate) fn amplitude_at(&self, step: usize) -> f64 {
+        let idx = self.steps.partition_point(|recorded| *recorded < step);
+
+        if self.steps.is_empty() {
+            0.
+        } else if idx == 0 {
+            self.amplitude[0]
+        } else if idx == self.steps.len() {
+            self.amplitude[idx - 1]
+        } else if self.steps[idx] == step {
+            self.amplitude[idx]
+        } else {
+            let (s0, s1) = (self.steps[idx - 1] as f64, self.steps[idx] as f64);
+            let (a0, a1) = (self.amplitude[idx - 1], self.amplitude[idx]);
+            a0 + (a1 - a0) * (step as f64 - s0) / (s1 - s0)
+        }
+    }
+}
+
+#[test]
+fn solver_output_steps() {
+    assert_eq!(output_steps(100, 450), vec![100, 200, 300, 400]);
+    assert_eq!(output_steps(0, 450), Vec::<usize>::new());
+}
+
+#[test]
+fn parse_and_interpolate_actuation_log() {
+    let log = "# written by the RL controller\nstep, time, amplitude\n0, 0.0, 0.0\n20, 0.1, 1.0\n10, 0.05, 0.5\n\n40.0, 0.2, -1.0\n";
+    let history = ActuationHistory::parse_csv(log).unwrap();
+
+    assert_eq!(history.steps, vec![0, 10, 20, 40]);
+    assert_eq!(history.amplitude, vec![0.0, 0.5, 1.0, -1.0]);
+
+    assert_eq!(history.amplitude_at(15), 0.75);
+    assert_eq!(history.amplitude_at(30), 0.0);
+    assert_eq!(history.amplitude_at(100), -1.0);
+}
@@ -1,15 +1,29 @@
+//! render the span averages of a run to PNG frames of an animation
+//!
+//! frames are read from the `span_averages.h5` file written by `spans-to-hdf5` and drawn on the
+//! mesh stored in it, see [`crate::render`].
+
 use crate::prelude::*;
+use crate::render;
 use anyhow::Result;
+use ndarray::s;
 use rayon::prelude::*;
 
-use xshell::{cmd, Shell};
-
-const NUM_THREADS: usize = 4;
-const ANIMATE_SCRIPT: &str = "/home/brooks/github/streams-utils/analysis/scripts/animate.jl";
-
 pub(crate) fn animate(args: cli::Animate) -> Result<()> {
     let animation_output_folder = args.data_folder.join("animation");
 
+    if args.decimate == 0 {
+        anyhow::bail!("--decimate must be at least 1");
+    }
+
+    if args.width < render::MIN_WIDTH || args.height < render::MIN_HEIGHT {
+        anyhow::bail!(
+            "frames must be at least {}x{} pixels",
+            render::MIN_WIDTH,
+            render::MIN_HEIGHT
+        );
+    }
+
     if animation_output_folder.exists() {
         std::fs::remove_dir_all(&animation_output_folder).ok();
     }
@@ -21,192 +35,222 @@ pub(crate) fn animate(args: cli::Animate) -> Result<()> {
         )
     })?;
 
-    let span_averages_path = args.data_folder.join("span_averages.h5");
-
-    let span_averages_file = hdf5::File::open(&span_averages_path)
-        .with_context(|| format!("failed to open file {}", span_averages_path.display()))?;
-
-    let averages = span_averages_file
-        .dataset("span_average")
-        .with_context(|| {
-            format!(
-                "failed to find span_average dataset in {}",
-                span_averages_path.display()
-            )
+    let span_averages_path = args.data_folder.join(crate::snapshots::SPAN_AVERAGES_FILE);
+    let span_averages = SpanAveragesFile::open(&span_averages_path, args.field)?;
+
+    let frames = (0..span_averages.num_snapshots)
+        .step_by(args.decimate)
+        .collect::<Vec<_>>();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()?;
+
+    let limits = match (args.vmin, args.vmax) {
+        (Some(low), Some(high)) => (low, high),
+        (low, high) => {
+            let (min, max) = span_averages.range(&frames)?;
+            let (min, max) = if render::is_diverging(args.colormap) {
+                let extent = min.abs().max(max.abs());
+                (-extent, extent)
+            } else {
+                (min, max)
+            };
+
+            (low.unwrap_or(min), high.unwrap_or(max))
+        }
+    };
+
+    // a constant field would otherwise divide by zero when mapped to the colormap
+    let limits = if limits.1 > limits.0 {
+        limits
+    } else {
+        (limits.0 - 0.5, limits.0 + 0.5)
+    };
+
+    println!(
+        "rendering {} frames of {} with color limits {:?}",
+        frames.len(),
+        args.field.dataset_name(),
+        limits
+    );
+
+    let plot = render::FieldPlot::new(
+        args.width,
+        args.height,
+        span_averages.x.clone(),
+        span_averages.y.clone(),
+        args.colormap,
+        limits,
+        args.field.dataset_name().to_string(),
+    );
+
+    let total = frames.len();
+
+    // hdf5 reads are serialized by the library, so frames are read in batches on this thread and
+    // only rendered in parallel
+    for (batch_idx, batch) in frames.chunks(utils::BATCH_SIZE).enumerate() {
+        let fields = batch
+            .iter()
+            .map(|snapshot_idx| span_averages.read(*snapshot_idx))
+            .collect::<Result<Vec<_>>>()?;
+
+        pool.install(|| {
+            batch
+                .par_iter()
+                .zip(fields.par_iter())
+                .enumerate()
+                .try_for_each(|(offset, (snapshot_idx, field))| -> Result<()> {
+                    let canvas =
+                        plot.render(field.view(), &span_averages.annotation(*snapshot_idx));
+
+                    // frames are numbered consecutively so that they can be passed to an encoder
+                    let frame_idx = batch_idx * utils::BATCH_SIZE + offset;
+                    let path = animation_output_folder.join(format!("anim_{frame_idx:05}.png"));
+                    canvas.save_png(&path)
+                })
         })?;
 
-    let num_writes = averages.shape()[0];
-
-    let partitions = partition_animation_indicies(NUM_THREADS, num_writes);
-
-    let folder_list = args.data_folder.display().to_string();
-    let decimate = args.decimate.to_string();
-
-    partitions.into_par_iter().for_each(|partition| {
-        let sh = Shell::new().unwrap();
-        let AnimationSpan {
-            start_idx,
-            end_idx,
-            cpu_number,
-        } = partition;
-        let start_idx = start_idx.to_string();
-        let end_idx = end_idx.to_string();
-
-        // julia needs about 30 seconds to compile each thread, and each thread takes quite a bit
-        // of memory to compile so we stagger them here
-        std::thread::sleep(std::time::Duration::from_secs(30 * cpu_number as u64));
-
-        let cmd = cmd!(
-            sh,
-            "julia {ANIMATE_SCRIPT} {start_idx} {end_idx} {decimate} {folder_list}"
+        println!(
+            "rendered {}/{total} frames",
+            batch_idx * utils::BATCH_SIZE + batch.len()
         );
-        cmd.run().unwrap();
-    });
+    }
 
-    if args.decimate != 1 {
-        reorganize_folder(&animation_output_folder)?;
+    if let Some(format) = args.encode {
+        let output = args
+            .data_folder
+            .join("animation")
+            .with_extension(format.extension());
+        crate::encode::encode_frames(
+            &animation_output_folder,
+            &output,
+            format,
+            args.framerate,
+            args.quality,
+        )?;
     }
 
     Ok(())
 }
 
-fn reorganize_folder(animation_folder: &Path) -> Result<()> {
-    let mut entries = walkdir::WalkDir::new(&animation_folder)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|f| f.file_type().is_file())
-        .map(|f| f.into_path())
-        .collect::<Vec<PathBuf>>();
-
-    entries.sort_unstable();
-
-    for (idx, file) in entries.into_iter().enumerate() {
-        let new_path = animation_folder.join(format!("anim_{idx:05}.png"));
+/// the dataset of the field being animated, and the metadata of every frame
+struct SpanAveragesFile {
+    path: PathBuf,
+    dataset: hdf5::Dataset,
+    num_snapshots: usize,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    steps: Vec<u64>,
+    time: Vec<f64>,
+}
 
-        std::fs::rename(&file, &new_path).with_context(|| {
+impl SpanAveragesFile {
+    fn open(path: &Path, field: cli::AnimationField) -> Result<Self> {
+        let file = hdf5::File::open(path).with_context(|| {
             format!(
-                "failed to rename {} to {}",
-                file.display(),
-                new_path.display()
+                "failed to open file {}. Run spans-to-hdf5 to create it",
+                path.display()
             )
         })?;
-    }
-
-    Ok(())
-}
 
-fn join_folders_to_list(folder_list: &[PathBuf]) -> String {
-    let mut out = String::new();
-
-    for folder in folder_list {
-        out.push_str(&folder.display().to_string());
-        out.push(' ');
+        let dataset = |name: &str| {
+            file.dataset(name)
+                .with_context(|| format!("failed to find {name} dataset in {}", path.display()))
+        };
+
+        let read_f64 = |name: &str| -> Result<Vec<f64>> {
+            dataset(name)?
+                .read_raw::<f64>()
+                .with_context(|| format!("failed to read {name} from {}", path.display()))
+        };
+
+        // files written by older versions of `spans-to-hdf5` store every field in a single
+        // `span_average` dataset, without the grid and times that frames are drawn with
+        if !file.link_exists(field.dataset_name()) && file.link_exists("span_average") {
+            anyhow::bail!(
+                "{} stores the fields in the legacy span_average dataset. Run spans-to-hdf5 again \
+                to rewrite it with a dataset per field",
+                path.display()
+            );
+        }
+
+        let field_dataset = dataset(field.dataset_name())?;
+        let num_snapshots = field_dataset.shape()[0];
+
+        let steps = dataset("step")?
+            .read_raw::<u64>()
+            .with_context(|| format!("failed to read step from {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            dataset: field_dataset,
+            num_snapshots,
+            x: read_f64("x")?,
+            y: read_f64("y")?,
+            steps,
+            time: read_f64("time")?,
+        })
     }
 
-    out
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct AnimationSpan {
-    cpu_number: usize,
-    start_idx: usize,
-    end_idx: usize,
-}
-
-fn partition_animation_indicies(num_cpus: usize, total_indicies: usize) -> Vec<AnimationSpan> {
-    let estimation = total_indicies / num_cpus;
-
-    let mut last_end_idx = 0;
+    /// the `(nx, ny)` field of a single snapshot
+    fn read(&self, idx: usize) -> Result<Array2> {
+        self.dataset
+            .read_slice(s![idx, .., ..])
+            .with_context(|| format!("failed to read snapshot {idx} of {}", self.path.display()))
+    }
 
-    let mut spans = Vec::new();
+    /// the smallest and largest (finite) values over all of the given snapshots
+    fn range(&self, indices: &[usize]) -> Result<(f64, f64)> {
+        let mut range = (f64::INFINITY, f64::NEG_INFINITY);
 
-    for cpu_number in 0..(num_cpus - 1) {
-        let start_idx = last_end_idx + 1;
-        let end_idx = start_idx + estimation - 1;
+        for idx in indices {
+            for value in self.read(*idx)?.iter().filter(|value| value.is_finite()) {
+                range = (range.0.min(*value), range.1.max(*value));
+            }
+        }
 
-        last_end_idx = end_idx;
+        if range.0 > range.1 {
+            anyhow::bail!(
+                "{} has no finite values to set the color limits from",
+                self.path.display()
+            );
+        }
 
-        spans.push(AnimationSpan {
-            start_idx,
-            end_idx,
-            cpu_number,
-        });
+        Ok(range)
     }
 
-    let start_idx = last_end_idx + 1;
-    let end_idx = total_indicies;
-
-    spans.push(AnimationSpan {
-        start_idx,
-        end_idx,
-        cpu_number: num_cpus - 1,
-    });
+    /// the time (or the step, if the time is unknown) of a snapshot
+    fn annotation(&self, idx: usize) -> String {
+        let step = self.steps.get(idx).copied().unwrap_or(idx as u64);
 
-    spans
+        match self.time.get(idx) {
+            Some(time) if time.is_finite() => format!("t = {time:.3}  (step {step})"),
+            _ => format!("step {step}"),
+        }
+    }
 }
 
 #[test]
-fn even_cpu_split() {
-    let cpus = 4;
-    let total_indicies = 16;
-
-    let partition = partition_animation_indicies(cpus, total_indicies);
-
-    let expected = vec![
-        AnimationSpan {
-            cpu_number: 0,
-            start_idx: 1,
-            end_idx: 4,
-        },
-        AnimationSpan {
-            cpu_number: 1,
-            start_idx: 5,
-            end_idx: 8,
-        },
-        AnimationSpan {
-            cpu_number: 2,
-            start_idx: 9,
-            end_idx: 12,
-        },
-        AnimationSpan {
-            cpu_number: 3,
-            start_idx: 13,
-            end_idx: 16,
-        },
-    ];
-
-    assert_eq!(partition, expected);
-}
+fn legacy_span_averages_file_is_an_error() {
+    let path =
+        std::env::temp_dir().join(format!("streams_utils_animate_{}.h5", std::process::id()));
+
+    {
+        let file = hdf5::File::create(&path).unwrap();
+        file.new_dataset::<f64>()
+            .shape((2, 5, 4, 3))
+            .create("span_average")
+            .unwrap();
+    }
 
-#[test]
-fn noneven_cpu_split() {
-    let cpus = 4;
-    let total_indicies = 18;
-
-    let partition = partition_animation_indicies(cpus, total_indicies);
-
-    let expected = vec![
-        AnimationSpan {
-            cpu_number: 0,
-            start_idx: 1,
-            end_idx: 4,
-        },
-        AnimationSpan {
-            cpu_number: 1,
-            start_idx: 5,
-            end_idx: 8,
-        },
-        AnimationSpan {
-            cpu_number: 2,
-            start_idx: 9,
-            end_idx: 12,
-        },
-        AnimationSpan {
-            cpu_number: 3,
-            start_idx: 13,
-            end_idx: 18,
-        },
-    ];
-
-    assert_eq!(partition, expected);
+    let error = SpanAveragesFile::open(&path, cli::AnimationField::Rho)
+        .err()
+        .unwrap()
+        .to_string();
+
+    std::fs::remove_file(&path).ok();
+
+    assert!(error.contains("span_average"), "{error}");
+    assert!(error.contains("spans-to-hdf5"), "{error}");
 }
@@ -14,8 +14,43 @@ pub struct SpanVtkInformation {
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum BinaryToVtkError {
-    #[error("Extra data present in the binary file ({0} points)")]
-    ExtraData(usize),
+    #[error("{0}")]
+    Truncated(utils::TruncatedBytes),
+    #[error("Data ended at proc {proc}, i = {i}, j = {j}. Expected {expected_bytes} bytes but the file has {actual_bytes} bytes")]
+    MissingData {
+        proc: usize,
+        i: usize,
+        j: usize,
+        expected_bytes: usize,
+        actual_bytes: usize,
+    },
+    #[error("Extra data present in the binary file. Expected {expected_bytes} bytes but the file has {actual_bytes} bytes")]
+    ExtraData {
+        expected_bytes: usize,
+        actual_bytes: usize,
+    },
+}
+
+#[derive(Debug, thiserror::Error, Constructor)]
+#[error("failed to convert span binary {}: {error}", path.display())]
+/// an error converting a span average .binary file, along with the file that caused it
+pub(crate) struct SpanBinaryError {
+    path: PathBuf,
+    error: BinaryToVtkError,
+}
+
+/// read a span average .binary file written by the solver
+pub(crate) fn read_span_binary(path: &Path, config: &Config) -> Result<SpanVtkInformation, Error> {
+    let file = utils::FloatFile::open(path)?;
+
+    let floats = file
+        .floats()
+        .map_err(|e| SpanBinaryError::new(path.to_owned(), BinaryToVtkError::Truncated(e)))?;
+
+    let data = convert_binary_to_vtk_information(&floats, config)
+        .map_err(|e| SpanBinaryError::new(path.to_owned(), e))?;
+
+    Ok(data)
 }
 
 /// load information from a .binary file to a format that can be processed in paraview
@@ -51,46 +86,71 @@ pub(crate) enum BinaryToVtkError {
 /// w   @ (1,1) + PROC 1
 /// E   @ (1,1) + PROC 1
 /// ```
+///
+/// When the domain is also split in z, every process writes the average over its own part of the
+/// span. Processes are ordered by their mpi rank, where the z process varies fastest
+/// (`rank = x_proc * mpi_z_split + z_proc`), and every process along z holds the same number of
+/// points, so the span average of the whole domain is the mean of their averages.
 pub(crate) fn convert_binary_to_vtk_information(
     data: &[f64],
     config: &Config,
 ) -> Result<SpanVtkInformation, BinaryToVtkError> {
-    let mut data = data.into_iter();
-
     let mut rho_arr = Array2::zeros((config.x_divisions, config.y_divisions));
     let mut velocity_arr = Array3::zeros((3, config.x_divisions, config.y_divisions));
     let mut energy_arr = Array2::zeros((config.x_divisions, config.y_divisions));
 
     let nx_proc = config.x_divisions / config.mpi_x_split;
     let ny = config.y_divisions;
+    let z_split = config.mpi_z_split;
+
+    let expected_bytes = 8 * 5 * config.nproc() * nx_proc * ny;
+    let actual_bytes = 8 * data.len();
+
+    // every point has rho, u, v, w, energy
+    let mut points = data.chunks(5);
+
+    for x_proc in 0..config.mpi_x_split {
+        for z_proc in 0..z_split {
+            for i_proc in 0..nx_proc {
+                for j in 0..ny {
+                    // scale the current x value to the number of the process that we are
+                    // dealing with
+                    let i = (nx_proc * x_proc) + i_proc;
+
+                    let (rho, u, v, w, energy) = match points.next() {
+                        Some(&[rho, u, v, w, energy]) => (rho, u, v, w, energy),
+                        _ => {
+                            return Err(BinaryToVtkError::MissingData {
+                                proc: x_proc * z_split + z_proc,
+                                i,
+                                j,
+                                expected_bytes,
+                                actual_bytes,
+                            })
+                        }
+                    };
+
+                    // each process along z contributes an equal share of the span average
+                    let weight = 1. / z_split as f64;
+
+                    rho_arr[(i, j)] += rho * weight;
 
-    for proc_number in 0..config.mpi_x_split {
-        for i_proc in 0..nx_proc {
-            for j in 0..ny {
-                let rho = *data.next().unwrap();
-                let u = *data.next().unwrap();
-                let v = *data.next().unwrap();
-                let w = *data.next().unwrap();
-                let energy = *data.next().unwrap();
-
-                // scale the current x value to the number of the process that we are
-                // dealing with
-                let i = (nx_proc * proc_number) + i_proc;
-                *rho_arr.get_mut((i, j)).unwrap() = rho;
-
-                *velocity_arr.get_mut((0, i, j)).unwrap() = u;
-                *velocity_arr.get_mut((1, i, j)).unwrap() = v;
-                *velocity_arr.get_mut((2, i, j)).unwrap() = w;
-
-                *energy_arr.get_mut((i, j)).unwrap() = energy;
+                    velocity_arr[(0, i, j)] += u * weight;
+                    velocity_arr[(1, i, j)] += v * weight;
+                    velocity_arr[(2, i, j)] += w * weight;
+
+                    energy_arr[(i, j)] += energy * weight;
+                }
             }
         }
     }
 
     // If there is extra information left in the array then something bad has happened
-    let (size_left, _) = data.size_hint();
-    if data.next() != None {
-        return Err(BinaryToVtkError::ExtraData(size_left));
+    if points.next().is_some() {
+        return Err(BinaryToVtkError::ExtraData {
+            expected_bytes,
+            actual_bytes,
+        });
     }
 
     Ok(SpanVtkInformation {
@@ -107,7 +167,8 @@ pub(crate) fn convert_binary_to_vtk_information(
 fn check_binary_with_config() {
     let data = utils::bytes_to_float(include_bytes!(
         "../static/span_average_00010_average.binary"
-    ));
+    ))
+    .unwrap();
 
     let config_bytes = include_bytes!("../static/span_average_input.json");
     let config: Config = serde_json::from_slice(config_bytes).unwrap();
@@ -116,3 +177,52 @@ fn check_binary_with_config() {
 
     formatted_data.unwrap();
 }
+
+#[test]
+/// a file that ends early should report where the data ran out
+fn short_binary_reports_position() {
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.x_divisions = 4;
+    config.y_divisions = 3;
+    config.mpi_x_split = 2;
+
+    // one full process worth of data, and one point of the second process
+    let data = vec![0.; 5 * (2 * 3 + 1)];
+
+    match convert_binary_to_vtk_information(&data, &config) {
+        Err(BinaryToVtkError::MissingData {
+            proc,
+            i,
+            j,
+            expected_bytes,
+            actual_bytes,
+        }) => {
+            assert_eq!((proc, i, j), (1, 2, 1));
+            assert_eq!(expected_bytes, 8 * 5 * 12);
+            assert_eq!(actual_bytes, 8 * 5 * 7);
+        }
+        _ => panic!("expected missing data error"),
+    }
+}
+
+#[test]
+/// processes along z are averaged together, and processes along x are placed side by side
+fn z_processes_are_averaged() {
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.x_divisions = 2;
+    config.y_divisions = 1;
+    config.mpi_x_split = 2;
+    config.mpi_z_split = 2;
+
+    // ranks 0 and 1 hold x = 0, ranks 2 and 3 hold x = 1
+    let data = [1., 2., 3., 4.]
+        .iter()
+        .flat_map(|value| [*value; 5])
+        .collect::<Vec<_>>();
+
+    let span = convert_binary_to_vtk_information(&data, &config).unwrap();
+
+    assert_eq!(span.rho[[0, 0]], 1.5);
+    assert_eq!(span.rho[[1, 0]], 3.5);
+    assert_eq!(span.velocity[[2, 1, 0]], 3.5);
+}
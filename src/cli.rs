@@ -16,12 +16,24 @@ pub(crate) struct Args {
 pub(crate) enum Command {
     /// generate a config file (input.dat) for use in the solver
     ConfigGenerator(ConfigGenerator),
+    /// check a config against every validation rule and print a report of the violations
+    Validate(Validate),
+    /// print a config with every `extends` base merged in and every `--set` override applied
+    ConfigResolve(ConfigResolve),
+    /// estimate the memory, disk space and wall time that a config will need to run
+    Estimate(Estimate),
+    /// summarize an inflow boundary layer database (database_bl.dat)
+    DatabaseBl(DatabaseBl),
+    /// list the mpi splits that are valid for a grid, and nearby grids for splits that are not
+    Decompose(Decompose),
     /// run the solver once inside the apptainer container
     RunContainer(RunContainer),
     /// run an the apptainer solver locally
     RunLocal(RunLocal),
     /// parse probe data to .mat files
     Probe(ParseProbe),
+    /// compute frequency and spanwise spectra of probe data and write them to .mat files
+    ProbeSpectra(ProbeSpectra),
     /// convert a span average VTK file to a .mat file for analysis
     VtkToMat(VtkToMat),
     /// convert a partial solver folder with span binaries to VTK files.
@@ -29,6 +41,14 @@ pub(crate) enum Command {
     SpansToVtk(SpansToVtk),
     /// convert a flowfields.h5 file into a series of vtk files
     HDF5ToVtk(HDF5ToVtk),
+    /// pack every span average of a run into a single span_averages.h5 file
+    SpansToHdf5(SpansToHdf5),
+    /// proper orthogonal decomposition of the span average snapshots of a run
+    Pod(Pod),
+    /// dynamic mode decomposition (optionally with control) of the span average snapshots of a run
+    Dmd(Dmd),
+    /// reconstruct the time history of the blowing slot amplitude of a run
+    ActuationHistory(ReconstructActuation),
     Animate(Animate),
 }
 
@@ -41,6 +61,108 @@ pub(crate) struct Animate {
     /// number to adjust the range by when iterating over the slices. If indexing
     /// from 1:100, and decimate = 5 then it will iterate by 1:5:100
     pub(crate) decimate: usize,
+
+    #[clap(long, value_enum, default_value_t = AnimationField::Rho)]
+    /// field of the span averages to animate
+    pub(crate) field: AnimationField,
+
+    #[clap(long, value_enum, default_value_t = Colormap::Viridis)]
+    /// colormap that the field is drawn with
+    pub(crate) colormap: Colormap,
+
+    #[clap(long)]
+    /// lower limit of the colormap. Computed from every frame if not given
+    pub(crate) vmin: Option<f64>,
+
+    #[clap(long)]
+    /// upper limit of the colormap. Computed from every frame if not given
+    pub(crate) vmax: Option<f64>,
+
+    #[clap(long, default_value_t = 1600)]
+    /// width of each frame in pixels
+    pub(crate) width: u32,
+
+    #[clap(long, default_value_t = 600)]
+    /// height of each frame in pixels
+    pub(crate) height: u32,
+
+    #[clap(long)]
+    /// maximum number of frames rendered at once. Defaults to the number of cores
+    pub(crate) jobs: Option<usize>,
+
+    #[clap(long, value_enum)]
+    /// encode the frames to a video in the data folder (animation.mp4, .webm, .gif or .png).
+    /// mp4 and webm require ffmpeg, gif uses ffmpeg if it is installed, and apng never does
+    pub(crate) encode: Option<VideoFormat>,
+
+    #[clap(long, default_value_t = 30)]
+    /// frames per second of the encoded video
+    pub(crate) framerate: u32,
+
+    #[clap(long, default_value_t = 75, value_parser = clap::value_parser!(u8).range(0..=100))]
+    /// quality of the encoded video, from 0 (smallest file) to 100 (best quality)
+    pub(crate) quality: u8,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+/// formats that animation frames can be encoded to
+pub(crate) enum VideoFormat {
+    /// H.264 mp4 (requires ffmpeg)
+    Mp4,
+    /// VP9 webm (requires ffmpeg)
+    Webm,
+    /// animated gif
+    Gif,
+    /// animated png
+    Apng,
+}
+
+impl VideoFormat {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+            Self::Gif => "gif",
+            Self::Apng => "png",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+/// fields of a span averages file that can be animated
+pub(crate) enum AnimationField {
+    Rho,
+    U,
+    V,
+    W,
+    Energy,
+}
+
+impl AnimationField {
+    /// name of the dataset in a span averages file
+    pub(crate) fn dataset_name(&self) -> &'static str {
+        match self {
+            Self::Rho => "rho",
+            Self::U => "u",
+            Self::V => "v",
+            Self::W => "w",
+            Self::Energy => "energy",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+/// colormaps for rendering fields. Automatic limits of diverging colormaps are centered on zero
+pub(crate) enum Colormap {
+    Viridis,
+    Magma,
+    Inferno,
+    Gray,
+    /// diverging red to blue
+    #[value(name = "rdbu")]
+    RdBu,
+    /// diverging blue to red
+    Coolwarm,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -88,19 +210,30 @@ pub(crate) struct ConfigGenerator {
     #[clap(long, default_value_t = 150)]
     pub(crate) z_divisions: usize,
 
-    /// number of MPI divisions along the x axis. The config generated
-    /// will have 1 mpi division along the z axis as some extensions
-    /// to the code assume there are no z divisions.
+    /// number of MPI divisions along the x axis.
     ///
-    /// The value supplied to this argument MUST be used for the -np
+    /// `mpi-x-split * mpi-z-split` MUST be used for the -np
     /// argument in `mpirun`
     #[clap(long, default_value_t = 4)]
     pub(crate) mpi_x_split: usize,
 
+    /// number of MPI divisions along the z axis, for wide span runs
+    #[clap(long, default_value_t = 1)]
+    pub(crate) mpi_z_split: usize,
+
+    #[clap(long)]
+    /// choose the mpi split automatically: the best split of the grid that uses at most
+    /// `--mpi-x-split` ranks and fits in GPU memory
+    pub(crate) auto_split: bool,
+
     #[clap(long)]
     /// skip writing the actual config file
     pub(crate) dry: bool,
 
+    #[clap(flatten)]
+    /// the GPU that the config is validated against
+    pub(crate) gpu: GpuSelection,
+
     #[clap(long, default_value_t = 50_000)]
     /// number of steps for the solver to take
     pub(crate) steps: usize,
@@ -129,7 +262,9 @@ pub(crate) struct ConfigGenerator {
     pub(crate) snapshots_3d: bool,
 
     #[clap(long)]
-    /// save output to json format
+    /// save output to json format. Output paths ending in .json, .toml, .yaml or .yml are always
+    /// written as a config in the format of their extension, and other paths are written as an
+    /// input.dat for the solver unless this is set
     pub(crate) json: bool,
 
     #[clap(long)]
@@ -144,11 +279,13 @@ pub(crate) struct ConfigGenerator {
     #[clap(long)]
     pub(crate) python_flowfield_steps: Option<usize>,
 
-    /// (currently not well understood): it is required that nymax-wr > y-divisions
+    /// (currently not well understood): number of points in y in the wall resolved region near
+    /// the wall. Must be less than y-divisions
     #[clap(long, default_value_t = 201)]
     pub(crate) nymax_wr: usize,
 
-    /// (currently not well understood): it is required that rly-wr > y-length
+    /// (currently not well understood): height of the wall resolved region. Must be less than
+    /// y-length
     #[clap(long, default_value_t = 2.5)]
     pub(crate) rly_wr: f64,
 
@@ -174,6 +311,38 @@ pub(crate) struct ConfigGenerator {
     pub(crate) shock_impingement: f64,
 }
 
+#[derive(clap::Args, Debug, Clone, Default)]
+/// selects how much memory each GPU has when validating a config. Built in GPUs, and how to
+/// add more, are described in the `gpu` module
+pub(crate) struct GpuSelection {
+    #[clap(long, conflicts_with_all = ["gpu_memory", "no_gpu_check"])]
+    /// name of the GPU the solver will run on (such as 2080ti, v100-32gb or a100-80gb).
+    /// Defaults to the 2080ti
+    pub(crate) gpu: Option<String>,
+
+    #[clap(long, conflicts_with = "no_gpu_check")]
+    /// memory available on each GPU in megabytes, for GPUs without a profile
+    pub(crate) gpu_memory: Option<usize>,
+
+    #[clap(long)]
+    /// TOML file of additional GPU profiles. Defaults to ~/.config/streams-utils/gpus.toml
+    pub(crate) gpu_profiles: Option<PathBuf>,
+
+    #[clap(long)]
+    /// skip checking that the domain fits in GPU memory, for runs on CPUs
+    pub(crate) no_gpu_check: bool,
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
+/// changes to the fields of a config, applied after any `extends` bases are merged in
+pub(crate) struct ConfigOverrides {
+    #[clap(long = "set", value_name = "KEY.PATH=VALUE")]
+    /// set a field of the config, such as `--set mach_number=2.5` or
+    /// `--set blowing_bc.Constant.amplitude=0.2`. Values are parsed as json, and are otherwise
+    /// strings. May be given many times
+    pub(crate) set: Vec<String>,
+}
+
 impl ConfigGenerator {
     /// create a default config to be written to a given path
     pub(crate) fn with_path(output_path: PathBuf) -> Self {
@@ -197,7 +366,10 @@ impl ConfigGenerator {
             //z_divisions: 256,
             z_divisions: 150,
             mpi_x_split: 4,
+            mpi_z_split: 1,
+            auto_split: false,
             dry: false,
+            gpu: GpuSelection::default(),
             steps: 50_000,
             probe_io_steps: 0,
             span_average_io_steps: 100,
@@ -229,6 +401,7 @@ impl ConfigGenerator {
             z_length,
             z_divisions,
             mpi_x_split,
+            mpi_z_split,
             steps,
             probe_io_steps,
             span_average_io_steps,
@@ -258,6 +431,7 @@ impl ConfigGenerator {
             z_length,
             z_divisions,
             mpi_x_split,
+            mpi_z_split,
             steps,
             probe_io_steps,
             span_average_io_steps,
@@ -277,6 +451,127 @@ impl ConfigGenerator {
     }
 }
 
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ConfigResolve {
+    /// path to the config to resolve
+    pub(crate) config: PathBuf,
+
+    #[clap(long)]
+    /// file to write the resolved config to, in the format of its extension. Printed as json
+    /// if not given
+    pub(crate) output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct Validate {
+    /// path to the input.json config to check
+    #[clap(required_unless_present = "list_rules")]
+    pub(crate) config: Option<PathBuf>,
+
+    #[clap(flatten)]
+    /// the GPU that the config is validated against
+    pub(crate) gpu: GpuSelection,
+
+    #[clap(long)]
+    /// fail if there are any warnings, not only errors
+    pub(crate) deny_warnings: bool,
+
+    #[clap(long)]
+    /// list every rule with its id and severity instead of checking a config
+    pub(crate) list_rules: bool,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct DatabaseBl {
+    #[command(subcommand)]
+    pub(crate) mode: DatabaseBlMode,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum DatabaseBlMode {
+    /// print the size and range of an existing database, and plot one of its columns
+    Summarize(SummarizeDatabaseBl),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct SummarizeDatabaseBl {
+    /// path to the database_bl.dat file
+    pub(crate) database: PathBuf,
+
+    #[clap(long, default_value_t = 1)]
+    /// column to plot against y (the first column)
+    pub(crate) column: usize,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct Estimate {
+    /// path to the input.json config of the planned run
+    pub(crate) config: PathBuf,
+
+    #[clap(flatten)]
+    /// the GPU that the run is estimated on
+    pub(crate) gpu: GpuSelection,
+
+    #[clap(long)]
+    /// solver timestep, used to count the 3D VTK snapshots when the config has no fixed_dt
+    pub(crate) dt: Option<f64>,
+
+    #[clap(long)]
+    /// disk space available to the run, in gigabytes
+    pub(crate) disk_quota: Option<f64>,
+
+    #[clap(long)]
+    /// host memory available to the run, in gigabytes
+    pub(crate) memory_quota: Option<f64>,
+
+    #[clap(long)]
+    /// wall time limit of the job, in hours
+    pub(crate) time_limit: Option<f64>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct Decompose {
+    /// total number of points in the x direction
+    #[clap(long, default_value_t = 800)]
+    pub(crate) x_divisions: usize,
+
+    /// total number of points in the y direction
+    #[clap(long, default_value_t = 208)]
+    pub(crate) y_divisions: usize,
+
+    /// total number of points in the z direction
+    #[clap(long, default_value_t = 150)]
+    pub(crate) z_divisions: usize,
+
+    #[clap(long, default_value_t = 1)]
+    /// number of MPI divisions along the z axis, which is kept fixed
+    pub(crate) mpi_z_split: usize,
+
+    #[clap(long)]
+    /// number of GPUs available in x. Splits using more ranks than this are not listed
+    pub(crate) gpus: Option<usize>,
+
+    #[clap(long)]
+    /// desired split to suggest nearby grids for if it is not valid. Defaults to `--gpus`
+    pub(crate) split: Option<usize>,
+
+    #[clap(flatten)]
+    /// the GPU that each rank must fit on
+    pub(crate) gpu: GpuSelection,
+}
+
 #[derive(ValueEnum, Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum FlowType {
     ChannelFlow,
@@ -399,6 +694,51 @@ impl JetActuator {
         }
     }
 
+    /// the amplitude of the jet at a given time for actuators whose amplitude is a known function
+    /// of time.
+    ///
+    /// `None` is returned for actuators whose amplitude is chosen while the solver is running
+    pub(crate) fn deterministic_amplitude(&self, time: f64) -> Option<f64> {
+        match &self {
+            Self::None => Some(0.),
+            Self::Constant { amplitude, .. } => Some(*amplitude),
+            Self::Sinusoidal {
+                amplitude,
+                angular_frequency,
+                ..
+            } => Some(amplitude * (angular_frequency * time).sin()),
+            Self::DMDc { .. } => None,
+            Self::Adaptive { .. } => None,
+        }
+    }
+
+    /// the first and last x index of the blowing slot, if there is one
+    pub(crate) fn slot(&self) -> Option<(usize, usize)> {
+        match &self {
+            Self::None => None,
+            Self::Constant {
+                slot_start,
+                slot_end,
+                ..
+            }
+            | Self::Sinusoidal {
+                slot_start,
+                slot_end,
+                ..
+            }
+            | Self::DMDc {
+                slot_start,
+                slot_end,
+                ..
+            }
+            | Self::Adaptive {
+                slot_start,
+                slot_end,
+                ..
+            } => Some((*slot_start, *slot_end)),
+        }
+    }
+
     pub(crate) fn slot_end_as_streams_int(&self) -> i32 {
         match &self {
             Self::None => -1,
@@ -495,8 +835,20 @@ pub(crate) enum SbliMode {
 
 #[derive(Parser, Debug, Clone)]
 pub(crate) struct RunContainer {
-    // no arguments required, the number of MPI processes
-    // allowed is set based on the number required by the input file
+    // the number of MPI processes allowed is set based on the number required by the input file
+    #[clap(long)]
+    /// skip (and report) output files that cannot be converted while postprocessing instead of
+    /// stopping
+    pub(crate) keep_going: bool,
+
+    #[clap(long)]
+    /// the number of threads used to convert span files while postprocessing. Defaults to the
+    /// number of cores
+    pub(crate) jobs: Option<usize>,
+
+    #[clap(long, value_enum, default_value_t = ArrayFormat::Mat)]
+    /// file format of the mesh coordinates written to the matfiles folder
+    pub(crate) mesh_format: ArrayFormat,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -509,7 +861,7 @@ pub(crate) struct RunLocal {
     pub(crate) workdir: PathBuf,
 
     #[clap(long)]
-    /// input.json file to load into the solver
+    /// config file (json, toml or yaml) to load into the solver
     pub(crate) config: PathBuf,
 
     #[clap(long)]
@@ -520,6 +872,10 @@ pub(crate) struct RunLocal {
     /// mount some python code into the container to run instead of the
     /// code contained in the solver image
     pub(crate) python_mount: Option<PathBuf>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
 }
 
 #[derive(Parser, Debug, Clone, Constructor)]
@@ -527,25 +883,129 @@ pub(crate) struct ParseProbe {
     /// mode to run the case generation with
     pub(crate) probe_directory: PathBuf,
 
-    /// location where .mat files will be written
+    /// location where the probe files will be written
     pub(crate) output_directory: PathBuf,
 
     /// config json file that was used to generate probe data
     #[clap(long)]
     pub(crate) config: PathBuf,
+
+    /// folder containing the x.dat, y.dat, and z.dat mesh files, used to record the physical
+    /// location of each probe. Defaults to the folder containing the config file
+    #[clap(long)]
+    pub(crate) mesh: Option<PathBuf>,
+
+    /// skip (and report) probe files that cannot be parsed instead of stopping
+    #[clap(long)]
+    pub(crate) keep_going: bool,
+
+    /// file format that each probe is written in
+    #[clap(long, value_enum, default_value_t = ProbeFormat::Mat)]
+    pub(crate) format: ProbeFormat,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum ProbeFormat {
+    /// MATLAB v5 .mat file
+    Mat,
+    /// chunked and compressed HDF5 file
+    Hdf5,
+    /// NetCDF-4 file. Requires building with the `netcdf` feature
+    Netcdf,
+    /// NumPy .npz archive
+    Npz,
+}
+
+impl ProbeFormat {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Self::Mat => "mat",
+            Self::Hdf5 => "h5",
+            Self::Netcdf => "nc",
+            Self::Npz => "npz",
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ProbeSpectra {
+    /// directory containing the span_probe .binary files written by the solver
+    pub(crate) probe_directory: PathBuf,
+
+    /// location where the spectra .mat files will be written
+    pub(crate) output_directory: PathBuf,
+
+    /// config json file that was used to generate probe data
+    #[clap(long)]
+    pub(crate) config: PathBuf,
+
+    /// the solver timestep. Required if the config file does not specify a `fixed_dt`.
+    ///
+    /// the time between probe samples is `dt * probe_io_steps`
+    #[clap(long)]
+    pub(crate) dt: Option<f64>,
+
+    /// number of samples in each Welch segment. Clipped to the number of probe files
+    #[clap(long, default_value_t = 256)]
+    pub(crate) segment_length: usize,
+
+    /// fraction of each Welch segment that overlaps with the next segment
+    #[clap(long, default_value_t = 0.5)]
+    pub(crate) overlap: f64,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
 }
 
 #[derive(Parser, Debug, Clone, Constructor)]
 pub(crate) struct VtkToMat {
-    /// all the input files to write to the output directory
+    /// all the input files to write to the output directory. Either span average .vtr files or
+    /// a single span_averages.h5 file written by `spans-to-hdf5`
     pub(crate) input_files: Vec<PathBuf>,
 
     #[clap(long)]
     pub(crate) config: PathBuf,
 
-    /// .mat file that is exported
+    /// file that is exported
     #[clap(long)]
     pub(crate) output_file: PathBuf,
+
+    /// file format of the exported file
+    #[clap(long, value_enum, default_value_t = ArrayFormat::Mat)]
+    pub(crate) format: ArrayFormat,
+
+    /// folder containing the x.dat and y.dat mesh files. Defaults to the folder of the config
+    #[clap(long)]
+    pub(crate) mesh: Option<PathBuf>,
+
+    /// also export the pressure and temperature derived from the conserved variables
+    #[clap(long)]
+    pub(crate) derived: bool,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+/// formats for exporting plain arrays
+pub(crate) enum ArrayFormat {
+    /// MATLAB v5 .mat file
+    Mat,
+    /// NumPy .npy file. Outputs with more than one array are written to one file per array
+    Npy,
+    /// NumPy .npz archive containing a .npy file for each array
+    Npz,
+    /// MATLAB v7.3 .mat file (HDF5 based), which is not limited to 2 GB variables. Large
+    /// outputs are streamed to the file instead of being held in memory
+    Mat73,
+    /// plain HDF5 file. Large outputs are streamed to the file instead of being held in memory
+    Hdf5,
 }
 
 #[derive(Parser, Debug, Clone, Constructor)]
@@ -557,6 +1017,38 @@ pub(crate) struct SpansToVtk {
     #[clap(long)]
     /// remove the old binary files after converting to
     pub(crate) clean_binary: bool,
+
+    #[clap(long)]
+    /// skip (and report) span files that cannot be converted instead of stopping
+    pub(crate) keep_going: bool,
+
+    #[clap(long)]
+    /// the number of threads used to convert span files. Defaults to the number of cores
+    pub(crate) jobs: Option<usize>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct SpansToHdf5 {
+    /// the path to the solver results. Should contain the input.json file, x.dat, y.dat, z.dat
+    /// as well as a ./spans/ folder containing span average .binary (or .vtr) files
+    pub(crate) solver_results: PathBuf,
+
+    /// the file to write. Defaults to span_averages.h5 in the solver results
+    #[clap(long)]
+    pub(crate) output: Option<PathBuf>,
+
+    /// the solver timestep, used to compute the time of each span average. Required if the
+    /// config file does not specify a `fixed_dt`
+    #[clap(long)]
+    pub(crate) dt: Option<f64>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
 }
 
 #[derive(Parser, Debug, Clone, Constructor)]
@@ -568,4 +1060,109 @@ pub(crate) struct HDF5ToVtk {
     /// This folder should contain a flowfields.h5 file. Results are written to a `vtks` folder
     /// within solver-results
     pub(crate) solver_results: PathBuf,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct Pod {
+    /// the path to the solver results. Should contain the input.json file, x.dat, y.dat, z.dat
+    /// as well as either a span_averages.h5 file or a ./spans/ folder containing span average
+    /// .vtr files
+    pub(crate) solver_results: PathBuf,
+
+    /// folder where the modes (pod_mode_XXX.vtr) and energy / temporal coefficients (pod.mat)
+    /// are written
+    pub(crate) output_directory: PathBuf,
+
+    /// number of modes to write to vtk files
+    #[clap(long, default_value_t = 10)]
+    pub(crate) modes: usize,
+
+    /// decompose the snapshots directly instead of subtracting the temporal mean first
+    #[clap(long)]
+    pub(crate) keep_mean: bool,
+
+    /// use an unweighted inner product instead of weighting each point by the area of its cell
+    /// on the (non uniform) mesh
+    #[clap(long)]
+    pub(crate) unweighted: bool,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct Dmd {
+    /// the path to the solver results. Should contain the input.json file, x.dat, y.dat, z.dat
+    /// as well as either a span_averages.h5 file or a ./spans/ folder containing span average
+    /// .vtr files
+    pub(crate) solver_results: PathBuf,
+
+    /// folder where the modes (dmd_mode_XXX.vtr) and spectrum (dmd.mat) are written
+    pub(crate) output_directory: PathBuf,
+
+    /// the decomposition to compute
+    #[clap(long, value_enum, default_value_t = DmdMethod::Exact)]
+    pub(crate) method: DmdMethod,
+
+    /// number of modes to keep (the rank that the snapshot matrix is truncated to)
+    #[clap(long, default_value_t = 10)]
+    pub(crate) rank: usize,
+
+    /// (DMDc only) rank that the snapshots stacked with the actuation signal are truncated to.
+    /// Defaults to `rank + 1`
+    #[clap(long)]
+    pub(crate) input_rank: Option<usize>,
+
+    /// the solver timestep. Required if the config file does not specify a `fixed_dt`.
+    #[clap(long)]
+    pub(crate) dt: Option<f64>,
+
+    /// (DMDc only) csv file with `step` and `amplitude` columns recording the jet amplitude.
+    /// Required for actuators whose amplitude is chosen while the solver runs, otherwise
+    /// the amplitude is reconstructed from the actuator in the config file
+    #[clap(long)]
+    pub(crate) actuation_log: Option<PathBuf>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ReconstructActuation {
+    /// the path to the solver results. Should contain the input.json file. For RL runs,
+    /// the training / eval output folders in the config are also searched for here
+    pub(crate) solver_results: PathBuf,
+
+    /// folder where the reconstructed histories (.mat and .csv) are written
+    pub(crate) output_directory: PathBuf,
+
+    /// the solver timestep. Required if the config file does not specify a `fixed_dt`.
+    #[clap(long)]
+    pub(crate) dt: Option<f64>,
+
+    /// csv files with `step` and `amplitude` columns recording the jet amplitude. Overrides
+    /// the actuator in the config file and any logs found in the RL output folders
+    #[clap(long)]
+    pub(crate) log: Vec<PathBuf>,
+
+    #[clap(flatten)]
+    /// changes to the fields of the config
+    pub(crate) overrides: ConfigOverrides,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum DmdMethod {
+    /// exact DMD (Tu et al. 2014) of evenly spaced snapshots
+    Exact,
+    /// optimized DMD (Askham & Kutz 2018): eigenvalues and modes are fit to every snapshot with
+    /// variable projection. Snapshots do not need to be evenly spaced
+    Optimized,
+    /// DMD with control (Proctor et al. 2016) using the jet amplitude as the input
+    Dmdc,
 }
@@ -1,45 +1,17 @@
 use crate::prelude::*;
+use crate::validate::Severity;
 use cli::ConfigGenerator;
 use cli::FlowType;
 
-#[derive(thiserror::Error, Debug, From)]
-pub(crate) enum ConfigError {
-    #[error("There was an error with the z-divisions / mpi-x-split values chosen. The domain cannot be evenly split: {0}")]
-    MpiSplitX(MpiSplitX),
-    #[error("Domain requires too much memory: {0}")]
-    Memory(Memory),
-    #[error("{0}")]
-    Custom(String),
-}
-
-#[derive(Debug, Display, Constructor)]
-#[display(
-    fmt = "x divisions: {} mpi divisions: {} remainder: {}",
-    x_div,
-    split,
-    remainder
+#[derive(thiserror::Error, Debug)]
+#[error(
+    "the config breaks {} validation rule(s):\n{}",
+    .errors.len(),
+    crate::validate::format_violations(.errors)
 )]
-pub(crate) struct MpiSplitX {
-    x_div: usize,
-    split: usize,
-    remainder: usize,
-}
-
-#[derive(Debug, Display, Constructor)]
-#[display(fmt = "invalid number of mpi splits: {}", split)]
-pub(crate) struct MpiSplitZero {
-    split: usize,
-}
-
-#[derive(Debug, Display, Constructor)]
-#[display(
-    fmt = "gpu memory capacity: {}, memory required for simulation: {}",
-    gpu_memory_required,
-    required_memory
-)]
-pub(crate) struct Memory {
-    gpu_memory_required: Megabytes,
-    required_memory: Megabytes,
+pub(crate) struct ConfigError {
+    /// every violation of a rule with error severity
+    pub(crate) errors: Vec<crate::validate::Violation>,
 }
 
 #[derive(Debug, Display, PartialEq, PartialOrd, Clone, Copy)]
@@ -51,35 +23,46 @@ pub(crate) fn config_generator(args: ConfigGenerator) -> anyhow::Result<()> {
     let dry = args.dry;
     let json = args.json;
 
-    let config = args.into_serializable();
+    let gpu_memory = crate::gpu::selected_gpu_memory(&args.gpu)?;
+
+    let auto_split = args.auto_split;
+    let mut config = args.into_serializable();
 
-    // 11 gb to megabytes
-    // 11 gb is what is available on the 2080 TI available in the lab
-    let gpu_memory = Some(Megabytes(11 * 10usize.pow(3)));
+    if auto_split {
+        config.mpi_x_split = crate::decompose::auto_split(&config, gpu_memory)?;
+        println!("using mpi-x-split = {}", config.mpi_x_split);
+    }
 
     // validate that the parameters can be run on the gpu
-    config.validate(gpu_memory)?;
+    let warnings = config.validate(gpu_memory)?;
 
-    if !dry {
-        if json {
-            let file = std::fs::File::create(&output_path).with_context(|| {
-                format!(
-                    "failed to create json output file at {}",
-                    output_path.display()
-                )
-            })?;
-            serde_json::to_writer_pretty(file, &config).with_context(|| {
-                format!("failed to serialize data to json file. This should not happen")
-            })?;
-            Ok(())
-        } else {
-            _config_generator(&config, output_path)
-        }
-    } else {
-        Ok(())
+    for warning in &warnings {
+        println!("{warning}");
+    }
+
+    if let Ok(mesh) = crate::mesh::ExpectedMesh::from_config(&config) {
+        mesh.quality(config.reynolds_number).print();
+    }
+
+    if dry {
+        return Ok(());
+    }
+
+    // configs are written in the format of their extension, and everything else is an input.dat
+    // unless --json is passed
+    match ConfigFormat::from_extension(&output_path) {
+        Some(_) => config.to_file(&output_path),
+        None if json => config.to_file(&output_path),
+        None => _config_generator(&config, output_path),
     }
 }
 
+/// ghost points on each side of a rank's part of the domain (`ng` in input.dat)
+pub(crate) const GHOST_POINTS: usize = 3;
+
+/// simulation time between 3D VTK snapshots (`dtsave` in input.dat)
+pub(crate) const VTK_SAVE_INTERVAL: f64 = 5.;
+
 /// create a streams config file to be used in the solver
 pub(crate) fn _config_generator(config: &Config, output_path: PathBuf) -> anyhow::Result<()> {
     const CFL: f64 = 0.75;
@@ -116,13 +99,13 @@ pub(crate) fn _config_generator(config: &Config, output_path: PathBuf) -> anyhow
   {nx}          {ny}        {nz}
  
  Ny_wr(nymax_wr)     Ly_wr(rly_wr)      dy+_w  jbgrid
- {nymax_wr}                   {rly_wr}             .7       0
+ {nymax_wr}                   {rly_wr}             {dy_plus_wall}       {jbgrid}
 
  ng  visc_ord  ep_ord  weno_par (1==>ord_1,2==>ord_3, 3==>ord_5, 4==>ord_7)
-  3     6      6       3
+  {ghost_points}     6      6       3
  
  MPI_x_split     MPI_z_split
- {mpi_x_split}               1 
+ {mpi_x_split}               {mpi_z_split} 
 
  sensor_threshold   xshock_imp   deflec_shock    pgrad (0==>constant bulk)
   {shock_sensitivity}               {shock_imp}             {angle}              0.
@@ -140,7 +123,7 @@ pub(crate) fn _config_generator(config: &Config, output_path: PathBuf) -> anyhow
    10. 20. 30. 35. 40. 45. 50. 55. 60. 65.
  
  dtsave dtsave_restart  enable_plot3d   enable_vtk
-  5.       50.                0          {snapshots_3d}
+  {dtsave:.1}      50.                0          {snapshots_3d}
 
  rand_type
    -1
@@ -162,14 +145,19 @@ sbli_blowing_bc        slot_start_x_global     slot_end_x_global
         re = config.reynolds_number,
         angle = config.shock_angle,
         mpi_x_split = config.mpi_x_split,
+        mpi_z_split = config.mpi_z_split,
         steps = config.steps,
         probe_steps = config.probe_io_steps,
         span_average_steps = config.span_average_io_steps,
         sbli_blowing_bc = config.blowing_bc.blowing_bc_as_streams_int(),
         snapshots_3d = config.snapshots_3d as usize,
+        ghost_points = GHOST_POINTS,
+        dtsave = VTK_SAVE_INTERVAL,
         cfl = cfl,
         nymax_wr = config.nymax_wr,
         rly_wr = config.rly_wr,
+        dy_plus_wall = crate::mesh::DY_PLUS_WALL,
+        jbgrid = crate::mesh::JBGRID,
         slot_start = config.blowing_bc.slot_start_as_streams_int(),
         slot_end = config.blowing_bc.slot_end_as_streams_int(),
         shock_sensitivity = config.sensor_threshold,
@@ -226,14 +214,17 @@ pub(crate) struct Config {
     /// in streams, this parameter is nzmax
     pub(crate) z_divisions: usize,
 
-    /// number of MPI divisions along the x axis. The config generated
-    /// will have 1 mpi division along the z axis as some extensions
-    /// to the code assume there are no z divisions.
+    /// number of MPI divisions along the x axis.
     ///
-    /// The value supplied to this argument MUST be used for the -np
+    /// `mpi_x_split * mpi_z_split` MUST be used for the -np
     /// argument in `mpirun`
     pub(crate) mpi_x_split: usize,
 
+    /// number of MPI divisions along the z axis. Configs written before
+    /// z divisions were supported have 1
+    #[serde(default = "default_mpi_z_split")]
+    pub(crate) mpi_z_split: usize,
+
     /// number of steps for the solver to take
     pub(crate) steps: usize,
     
@@ -265,10 +256,13 @@ pub(crate) struct Config {
     /// how often to export full flowfields to hdf5 files (PYTHON ONLY!)
     pub(crate) python_flowfield_steps: Option<usize>,
 
-    /// (currently not well understood): it is required that nymax-wr > y-divisions
+    /// (currently not well understood): number of points in y in the wall resolved region near
+    /// the wall, see [`crate::mesh`] for an approximation of the grid. Must be less than
+    /// y-divisions
     pub(crate) nymax_wr: usize,
 
-    /// (currently not well understood): it is required that rly-wr > y-length
+    /// (currently not well understood): height of the wall resolved region. Must be less than
+    /// y-length
     pub(crate) rly_wr: f64,
 
     /// X locations for vertical probes (along different values of y) at a (X, _, Z) location.
@@ -288,122 +282,221 @@ pub(crate) struct Config {
 }
 
 impl Config {
-    /// load the config data at a given path with `serde_json`
-    pub(crate) fn from_path(path: &Path) -> Result<Self, Error> {
-        // load the config file specified
-        let config_bytes = fs::read(&path).map_err(|e| FileError::new(path.to_owned(), e))?;
-        let config: Config = serde_json::from_slice(&config_bytes)?;
-        Ok(config)
+    pub(crate) fn serialize(&self, format: ConfigFormat) -> anyhow::Result<String> {
+        let contents = match format {
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            // through json so that enums are written as tables, which is how they are read
+            ConfigFormat::Yaml => distribute::serde_yaml::to_string(&serde_json::to_value(self)?)?,
+        };
+        Ok(contents)
+    }
+
+    /// the solver timestep, either from `fixed_dt` or from a value supplied by the user
+    ///
+    /// a user supplied `dt` takes precedence over the config file
+    pub(crate) fn timestep(&self, dt: Option<f64>) -> anyhow::Result<f64> {
+        match (dt, self.fixed_dt) {
+            (Some(dt), _) => Ok(dt),
+            (None, Some(fixed_dt)) => Ok(fixed_dt),
+            (None, None) => anyhow::bail!(
+                "the config file does not specify a fixed_dt, the solver timestep must be passed with --dt"
+            ),
+        }
     }
 
     /// check all the parameters of the input file to guarantee that the given input
     /// file will (likely) work in the solver without runtime error
     ///
-    /// `max_gpu_mem` must only be specified if you are running the config on a gpu system
-    pub(crate) fn validate(&self, max_gpu_mem: Option<Megabytes>) -> Result<(), ConfigError> {
-        // make sure that the number of divisions with mpi is acceptable
-        let split_remainder = self.x_divisions % self.mpi_x_split;
-        if split_remainder != 0 {
-            return Err(MpiSplitX::new(self.x_divisions, self.mpi_x_split, split_remainder).into());
+    /// `max_gpu_mem` must only be specified if you are running the config on a gpu system.
+    ///
+    /// every rule in [`crate::validate`] is checked. Violations of error rules stop the solver
+    /// from running and are returned together as an error, while violations of warning rules
+    /// are returned
+    pub(crate) fn validate(
+        &self,
+        max_gpu_mem: Option<Megabytes>,
+    ) -> Result<Vec<crate::validate::Violation>, ConfigError> {
+        let (errors, warnings) = crate::validate::check(self, max_gpu_mem)
+            .into_iter()
+            .partition::<Vec<_>, _>(|violation| violation.severity == Severity::Error);
+
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(ConfigError { errors })
         }
+    }
 
-        if let Some(gpu_mem) = max_gpu_mem {
-            self.check_gpu_mem(gpu_mem)?;
-        }
+    /// memory used on each gpu, where every mpi rank has its own gpu and the domain is split
+    /// evenly in x and z between the ranks
+    pub(crate) fn gpu_memory_per_rank(&self) -> Megabytes {
+        gpu_memory_per_rank(
+            self.x_divisions,
+            self.y_divisions,
+            self.z_divisions,
+            self.mpi_x_split,
+            self.mpi_z_split,
+        )
+    }
 
-        // from config file
-        if self.y_divisions < self.nymax_wr {
-            return Err(ConfigError::Custom(format!(
-                "y-divisions ({}) must be greater than {}",
-                self.y_divisions, self.nymax_wr
-            )));
-        }
+    /// number of mpi ranks the solver runs with
+    pub(crate) fn nproc(&self) -> usize {
+        self.mpi_x_split * self.mpi_z_split
+    }
 
-        if self.y_length <= self.rly_wr {
-            return Err(ConfigError::Custom(format!(
-                "y-length ({}) must be greater than rly-wr {}",
-                self.y_length, self.rly_wr
-            )));
-        }
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
 
-        if self.y_divisions <= self.nymax_wr {
-            return Err(ConfigError::Custom(format!(
-                "y-divisions ({}) must be greater than nymax-wr {}",
-                self.y_divisions, self.nymax_wr
-            )));
-        }
+    /// write the config to a file, in the format given by its extension (see [`ConfigFormat`])
+    pub(crate) fn to_file<T: AsRef<Path>>(&self, path: T) -> anyhow::Result<()> {
+        let path = path.as_ref().to_owned();
 
-        if self.y_divisions % self.mpi_x_split != 0 {
-            return Err(ConfigError::Custom(format!(
-                "nymax (y-divisions) @ {} must be divisible by mpi-x-split @ {} (remainder: {})",
-                self.y_divisions,
-                self.mpi_x_split,
-                self.y_divisions % self.mpi_x_split
-            )));
-        }
+        let contents = self
+            .serialize(ConfigFormat::from_path(&path))
+            .with_context(|| {
+                format!(
+                    "failed to serialize config to file at path {}",
+                    path.display()
+                )
+            })?;
 
-        // make sure that the coordinates of the x probe and z probe make sense. There should
-        // be equal numbers of points for x probes as there are for z probes.
-        if self.probe_locations_x.len() != self.probe_locations_z.len() {
-            return Err(ConfigError::Custom(format!(
-                "There were not equal numbers of probe  locations ( (x,z) coordinates from --probe-locations-x and --probe_locations_z) there were {} x locations and {} locations.",
-                self.probe_locations_x.len(),
-                self.probe_locations_z.len(),
-            )));
-        }
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write config to {}", path.display()))?;
 
         Ok(())
     }
+}
 
-    /// check that there is enough memory available on the gpu to run the simulation
-    ///
-    /// this code is a replication of the memory checking code in fortran
-    fn check_gpu_mem(&self, max_gpu_mem: Megabytes) -> Result<(), ConfigError> {
-        // fortran memory checking code :
-        // gpu_used_mem = 43._mykind      ! Number of 3D arrays on GPU
-        // correction_factor = 1.5_mykind ! Safety margin
-        // gpu_used_mem = gpu_used_mem+correction_factor
-        // gpu_used_mem = gpu_used_mem*real((nx+2*ng),mykind)*real((ny+2*ng),mykind)*real((nz+2*ng),mykind)
-        // gpu_used_mem = gpu_used_mem*storage_size(1._mykind)/8._mykind/(1024._mykind**2)
-        let n_ghost = 3;
-        let mut gpu_used_mem = 43.;
-        // number of bytes for floating point
-        let n_bytes = 8usize;
-        gpu_used_mem += 1.5;
-        gpu_used_mem *= ((self.x_divisions + (2 * n_ghost))
-            * (self.y_divisions + (2 * n_ghost))
-            * (self.z_divisions + (2 * n_ghost))) as f64;
-        gpu_used_mem *= (n_bytes as f64) / (1024. * 1024.);
-        let gpu_mem_required = Megabytes(gpu_used_mem as usize);
-
-        if gpu_mem_required > max_gpu_mem {
-            return Err(Memory::new(gpu_mem_required, max_gpu_mem).into());
-        }
+/// file formats that a config can be stored in. TOML and YAML configs may contain comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
 
-        Ok(())
+impl ConfigFormat {
+    /// the format of a config file from its extension. Files that are not `.toml`, `.yaml` or
+    /// `.yml` are json
+    pub(crate) fn from_path(path: &Path) -> Self {
+        Self::from_extension(path).unwrap_or(Self::Json)
     }
 
-    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
-        serde_json::to_writer_pretty(writer, self)?;
-        Ok(())
+    /// the format of a path with a `.json`, `.toml`, `.yaml` or `.yml` extension
+    pub(crate) fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Some(Self::Json),
+            Some("toml") => Some(Self::Toml),
+            Some("yaml") | Some("yml") => Some(Self::Yaml),
+            _ => None,
+        }
     }
+}
 
-    pub(crate) fn to_file<T: AsRef<Path>>(&self, path: T) -> anyhow::Result<()> {
-        let path = path.as_ref().to_owned();
-        let mut file = std::fs::File::create(&path).with_context(|| {
-            format!(
-                "failed to serialize create file at {} for config",
-                path.display()
-            )
-        })?;
-
-        self.to_writer(&mut file).with_context(|| {
-            format!(
-                "failed to serialize config to file at path {}",
-                path.display()
-            )
-        })?;
+fn default_mpi_z_split() -> usize {
+    1
+}
 
-        Ok(())
+/// memory used on each gpu for a grid split into `mpi_x_split` ranks along x and `mpi_z_split`
+/// ranks along z
+pub(crate) fn gpu_memory_per_rank(
+    x_divisions: usize,
+    y_divisions: usize,
+    z_divisions: usize,
+    mpi_x_split: usize,
+    mpi_z_split: usize,
+) -> Megabytes {
+    // fortran memory checking code :
+    // gpu_used_mem = 43._mykind      ! Number of 3D arrays on GPU
+    // correction_factor = 1.5_mykind ! Safety margin
+    // gpu_used_mem = gpu_used_mem+correction_factor
+    // gpu_used_mem = gpu_used_mem*real((nx+2*ng),mykind)*real((ny+2*ng),mykind)*real((nz+2*ng),mykind)
+    // gpu_used_mem = gpu_used_mem*storage_size(1._mykind)/8._mykind/(1024._mykind**2)
+    let n_ghost = GHOST_POINTS;
+    let mut gpu_used_mem = 43.;
+    // number of bytes for floating point
+    let n_bytes = 8usize;
+    gpu_used_mem += 1.5;
+    // nx and nz in fortran are the number of points on a single rank
+    let nx = x_divisions / mpi_x_split.max(1);
+    let nz = z_divisions / mpi_z_split.max(1);
+    gpu_used_mem *=
+        ((nx + (2 * n_ghost)) * (y_divisions + (2 * n_ghost)) * (nz + (2 * n_ghost))) as f64;
+    gpu_used_mem *= (n_bytes as f64) / (1024. * 1024.);
+    Megabytes(gpu_used_mem as usize)
+}
+
+#[test]
+fn gpu_memory_is_per_rank() {
+    let mut config = ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.mpi_x_split = 1;
+    let single = config.gpu_memory_per_rank();
+
+    config.mpi_x_split = 4;
+    let split = config.gpu_memory_per_rank();
+
+    // ghost points are added to every rank, so the split is slightly more than a quarter
+    assert!(split.0 * 4 >= single.0);
+    assert!(split.0 * 3 < single.0);
+}
+
+#[test]
+fn config_formats_round_trip() {
+    let mut config = ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.fixed_dt = Some(0.001);
+    config.probe_locations_x = vec![100, 200];
+    config.probe_locations_z = vec![10, 20];
+    config.blowing_bc = cli::JetActuator::Sinusoidal {
+        amplitude: 0.5,
+        angular_frequency: std::f64::consts::PI,
+        slot_start: 100,
+        slot_end: 120,
+    };
+
+    let expected = serde_json::to_value(&config).unwrap();
+
+    for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+        let contents = config.serialize(format).unwrap();
+        let parsed = crate::config_resolve::parse_value(&contents, format).unwrap();
+        let parsed: Config = serde_json::from_value(parsed).unwrap();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            expected,
+            "{format:?}"
+        );
     }
 }
+
+#[test]
+fn config_format_from_extension() {
+    assert_eq!(
+        ConfigFormat::from_path(Path::new("input.json")),
+        ConfigFormat::Json
+    );
+    assert_eq!(
+        ConfigFormat::from_path(Path::new("base.toml")),
+        ConfigFormat::Toml
+    );
+    assert_eq!(
+        ConfigFormat::from_path(Path::new("case.yml")),
+        ConfigFormat::Yaml
+    );
+
+    // config-generator writes an input.dat for any other extension
+    assert_eq!(ConfigFormat::from_extension(Path::new("input.dat")), None);
+    assert_eq!(
+        ConfigFormat::from_path(Path::new("input.dat")),
+        ConfigFormat::Json
+    );
+
+    // comments are allowed in toml configs
+    let config = ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    let contents = format!(
+        "# a coarse case\n{}",
+        config.serialize(ConfigFormat::Toml).unwrap()
+    );
+    crate::config_resolve::parse_value(&contents, ConfigFormat::Toml).unwrap();
+}
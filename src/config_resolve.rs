@@ -0,0 +1,433 @@
+//! config inheritance and overrides
+//!
+//! a config file may contain an `extends` key with the path of a base config (relative to the
+//! file that extends it). The base is loaded first, and every field of the extending file is
+//! merged into it: tables (such as `blowing_bc`) are merged field by field, and every other value
+//! replaces the value of the base. A variant of an enum (such as `{"Sinusoidal": {...}}` for
+//! `blowing_bc`) replaces a different variant of the base instead of being merged with it. Bases
+//! may extend other bases, in any of the config formats.
+//!
+//! `--set key.path=value` overrides are applied to the merged config, in the order they are
+//! given. Dashes in keys are read as underscores, so `--set mach-number=2` works as well.
+
+use crate::config_generator::ConfigFormat;
+use crate::prelude::*;
+use serde_json::Value;
+
+/// key of a config file that names the config it extends
+const EXTENDS_KEY: &str = "extends";
+
+/// fields of the config that hold an enum. Variants with fields are serialized as a table with
+/// the name of the variant as its only key
+const ENUM_FIELDS: [&str; 1] = ["blowing_bc"];
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ResolveError {
+    #[error("{path} extends {base}, which already extends it")]
+    Cycle { path: String, base: String },
+    #[error("`extends` in {path} must be the path to a config file")]
+    Extends { path: String },
+    #[error("the config in {path} is not a table of fields")]
+    NotATable { path: String },
+    #[error("override `{0}` must be of the form key.path=value")]
+    Assignment(String),
+    #[error("override `{assignment}` indexes a list with `{index}`, which is not an index into its {len} elements")]
+    Index {
+        assignment: String,
+        index: String,
+        len: usize,
+    },
+    #[error("override `{assignment}` sets `{key}`, which is not a field of the config")]
+    UnknownField { assignment: String, key: String },
+}
+
+impl Config {
+    /// load the config at a given path, merging in the configs it extends and then applying
+    /// the overrides
+    pub(crate) fn load(path: &Path, overrides: &cli::ConfigOverrides) -> Result<Self, Error> {
+        let value = resolve(path, overrides)?;
+        let config: Config = serde_json::from_value(value)?;
+
+        // overrides of fields that do not exist would otherwise be silently ignored
+        check_override_fields(&config, overrides)?;
+
+        Ok(config)
+    }
+}
+
+/// the fully merged config at a path, with every override applied
+fn resolve(path: &Path, overrides: &cli::ConfigOverrides) -> Result<Value, Error> {
+    let mut value = load_with_bases(path, &mut Vec::new())?;
+
+    for assignment in &overrides.set {
+        apply_override(&mut value, assignment)?;
+    }
+
+    Ok(value)
+}
+
+/// parse the contents of a config file in any format to a generic value
+pub(crate) fn parse_value(contents: &str, format: ConfigFormat) -> Result<Value, Error> {
+    let value = match format {
+        ConfigFormat::Json => serde_json::from_str(contents)?,
+        ConfigFormat::Toml => toml::from_str(contents)?,
+        ConfigFormat::Yaml => distribute::serde_yaml::from_str(contents)?,
+    };
+    Ok(value)
+}
+
+/// load a config file and every base that it extends. `chain` holds the files that are
+/// currently being loaded, to find cycles
+fn load_with_bases(path: &Path, chain: &mut Vec<PathBuf>) -> Result<Value, Error> {
+    let contents = fs::read_to_string(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+    let mut value = parse_value(&contents, ConfigFormat::from_path(path))?;
+
+    let Value::Object(fields) = &mut value else {
+        return Err(ResolveError::NotATable {
+            path: path.display().to_string(),
+        }
+        .into());
+    };
+
+    let Some(extends) = fields.remove(EXTENDS_KEY) else {
+        return Ok(value);
+    };
+
+    let Value::String(base) = extends else {
+        return Err(ResolveError::Extends {
+            path: path.display().to_string(),
+        }
+        .into());
+    };
+
+    let base = path.parent().unwrap_or(Path::new("")).join(base);
+
+    // compare canonical paths so that a cycle is found however the paths are written
+    let canonical = fs::canonicalize(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+    let canonical_base = fs::canonicalize(&base).map_err(|e| FileError::new(base.clone(), e))?;
+
+    if chain.contains(&canonical_base) || canonical_base == canonical {
+        return Err(ResolveError::Cycle {
+            path: path.display().to_string(),
+            base: base.display().to_string(),
+        }
+        .into());
+    }
+
+    chain.push(canonical);
+    let mut merged = load_with_bases(&base, chain)?;
+    chain.pop();
+
+    merge(&mut merged, value);
+    Ok(merged)
+}
+
+/// whether the value of an enum field in the overlay is a different variant than in the base
+fn switches_variant(base: &Value, overlay: &Value) -> bool {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            overlay.keys().any(|variant| !base.contains_key(variant))
+        }
+        _ => false,
+    }
+}
+
+/// merge `overlay` into `base`. Tables are merged key by key, and every other value of the
+/// overlay replaces the value in the base
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    // a different variant of an enum replaces the variant of the base
+                    Some(existing)
+                        if ENUM_FIELDS.contains(&key.as_str())
+                            && switches_variant(existing, &value) =>
+                    {
+                        *existing = value
+                    }
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// apply a single `key.path=value` override
+fn apply_override(value: &mut Value, assignment: &str) -> Result<(), ResolveError> {
+    let (path, raw) = assignment
+        .split_once('=')
+        .filter(|(path, _)| !path.is_empty())
+        .ok_or_else(|| ResolveError::Assignment(assignment.to_owned()))?;
+
+    // `--set steps=100` is a number, while `--set blowing_bc=None` is the string "None"
+    let new = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_owned()));
+
+    let mut target = value;
+    // the field of the config that is set
+    let field = path.split('.').next().unwrap_or_default().replace('-', "_");
+
+    for (depth, key) in path.split('.').enumerate() {
+        target = match target {
+            Value::Array(items) => match key.parse::<usize>() {
+                Ok(index) if index < items.len() => &mut items[index],
+                _ => {
+                    return Err(ResolveError::Index {
+                        assignment: assignment.to_owned(),
+                        index: key.to_owned(),
+                        len: items.len(),
+                    })
+                }
+            },
+            target => {
+                let key = key.replace('-', "_");
+
+                // a value that is not a table (such as a blowing_bc of "None") is replaced by one,
+                // as is a different variant of an enum than the one being set
+                let replace = match &*target {
+                    Value::Object(fields) => {
+                        depth == 1
+                            && ENUM_FIELDS.contains(&field.as_str())
+                            && !fields.contains_key(&key)
+                    }
+                    _ => true,
+                };
+
+                if replace {
+                    *target = Value::Object(Default::default());
+                }
+
+                target
+                    .as_object_mut()
+                    .unwrap()
+                    .entry(key)
+                    .or_insert(Value::Null)
+            }
+        };
+    }
+
+    *target = new;
+    Ok(())
+}
+
+/// every override must set a field that the config has. Fields that are not part of the config
+/// (at any depth) are dropped when it is parsed, so they are missing once it is serialized again
+fn check_override_fields(
+    config: &Config,
+    overrides: &cli::ConfigOverrides,
+) -> Result<(), ResolveError> {
+    // every field is serialized, including fields that are `None`
+    let fields = serde_json::to_value(config).expect("a config always serializes to json");
+
+    for assignment in &overrides.set {
+        let path = assignment.split('=').next().unwrap_or_default();
+
+        let mut target = &fields;
+        let mut resolved = Vec::new();
+
+        for key in path.split('.') {
+            let key = key.replace('-', "_");
+
+            let next = match target {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+                target => target.get(&key),
+            };
+
+            resolved.push(key);
+
+            match next {
+                Some(next) => target = next,
+                None => {
+                    return Err(ResolveError::UnknownField {
+                        assignment: assignment.clone(),
+                        key: resolved.join("."),
+                    })
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn config_resolve(args: cli::ConfigResolve) -> anyhow::Result<()> {
+    let config = Config::load(&args.config, &args.overrides)
+        .with_context(|| format!("failed to resolve config at {}", args.config.display()))?;
+
+    match args.output {
+        Some(output) => config.to_file(&output),
+        None => {
+            println!("{}", config.serialize(ConfigFormat::Json)?);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+fn default_config_value() -> Value {
+    let config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    serde_json::to_value(config).unwrap()
+}
+
+#[test]
+fn merge_is_deep() {
+    let mut base = serde_json::json!({
+        "steps": 100,
+        "blowing_bc": {"Constant": {"amplitude": 1.0, "slot_start": 100, "slot_end": 120}},
+    });
+    let overlay = serde_json::json!({
+        "blowing_bc": {"Constant": {"amplitude": 0.5}},
+        "probe_locations_x": [1, 2],
+    });
+
+    merge(&mut base, overlay);
+
+    assert_eq!(
+        base,
+        serde_json::json!({
+            "steps": 100,
+            "blowing_bc": {"Constant": {"amplitude": 0.5, "slot_start": 100, "slot_end": 120}},
+            "probe_locations_x": [1, 2],
+        })
+    );
+}
+
+#[test]
+/// a study can switch the actuator of its base to a different type
+fn enum_variants_are_replaced() {
+    let sinusoidal = serde_json::json!({
+        "amplitude": 0.3,
+        "angular_frequency": 2.0,
+        "slot_start": 100,
+        "slot_end": 120,
+    });
+
+    let mut base = default_config_value();
+    base["blowing_bc"] = serde_json::json!({
+        "Constant": {"amplitude": 1.0, "slot_start": 100, "slot_end": 120}
+    });
+
+    // tables with a single field are not variants
+    let mut partial = serde_json::json!({"steps": 100});
+    merge(&mut partial, serde_json::json!({"mach_number": 3.0}));
+    assert_eq!(
+        partial,
+        serde_json::json!({"steps": 100, "mach_number": 3.0})
+    );
+
+    // and neither are tables with capitalized keys in fields that are not enums
+    let mut table = serde_json::json!({"table": {"A": 1}});
+    merge(&mut table, serde_json::json!({"table": {"B": 2}}));
+    assert_eq!(table, serde_json::json!({"table": {"A": 1, "B": 2}}));
+
+    let mut merged = base.clone();
+    merge(
+        &mut merged,
+        serde_json::json!({"blowing_bc": {"Sinusoidal": sinusoidal}}),
+    );
+    assert_eq!(
+        merged["blowing_bc"],
+        serde_json::json!({"Sinusoidal": sinusoidal})
+    );
+
+    let mut overridden = base;
+    for assignment in [
+        "blowing_bc.Sinusoidal.amplitude=0.3",
+        "blowing_bc.Sinusoidal.angular-frequency=2.0",
+        "blowing_bc.Sinusoidal.slot_start=100",
+        "blowing_bc.Sinusoidal.slot_end=120",
+    ] {
+        apply_override(&mut overridden, assignment).unwrap();
+    }
+    assert_eq!(merged, overridden);
+
+    let config: Config = serde_json::from_value(overridden).unwrap();
+    assert!(matches!(
+        config.blowing_bc,
+        cli::JetActuator::Sinusoidal { amplitude, .. } if amplitude == 0.3
+    ));
+}
+
+#[test]
+fn overrides_set_nested_fields() {
+    let mut value = default_config_value();
+    value["probe_locations_x"] = serde_json::json!([10, 20]);
+
+    for assignment in [
+        "mach-number=3",
+        "blowing_bc.Constant.amplitude=0.2",
+        "blowing_bc.Constant.slot_start=100",
+        "blowing_bc.Constant.slot_end=120",
+        "probe_locations_x.1=30",
+        "fixed_dt=null",
+    ] {
+        apply_override(&mut value, assignment).unwrap();
+    }
+
+    let config: Config = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(config.mach_number, 3.);
+    assert_eq!(config.blowing_bc.slot(), Some((100, 120)));
+    assert_eq!(config.probe_locations_x, [10, 30]);
+
+    assert!(apply_override(&mut value, "steps").is_err());
+    assert!(apply_override(&mut value, "probe_locations_x.5=1").is_err());
+}
+
+#[test]
+fn configs_extend_their_bases() {
+    let dir = std::env::temp_dir().join(format!("streams_utils_extends_{}", std::process::id()));
+    fs::create_dir_all(dir.join("cases")).unwrap();
+
+    let base = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    base.to_file(dir.join("base.json")).unwrap();
+
+    fs::write(
+        dir.join("cases/fine.toml"),
+        "# finer grid in x\nextends = \"../base.json\"\nx_divisions = 1600\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("cases/fast.yaml"),
+        "extends: fine.toml\nmach_number: 3.0\n",
+    )
+    .unwrap();
+
+    let overrides = cli::ConfigOverrides {
+        set: vec!["steps=10".into()],
+    };
+    let config = Config::load(&dir.join("cases/fast.yaml"), &overrides).unwrap();
+    assert_eq!(config.x_divisions, 1600);
+    assert_eq!(config.mach_number, 3.);
+    assert_eq!(config.steps, 10);
+    assert_eq!(config.y_divisions, base.y_divisions);
+
+    let typo = cli::ConfigOverrides {
+        set: vec!["stepz=10".into()],
+    };
+    assert!(Config::load(&dir.join("cases/fast.yaml"), &typo).is_err());
+
+    // a typo in a nested field would otherwise be dropped by the variant that it is part of
+    let nested_typo = cli::ConfigOverrides {
+        set: vec![
+            "blowing_bc.Constant.amplitude=0.2".into(),
+            "blowing_bc.Constant.slot_start=100".into(),
+            "blowing_bc.Constant.slot_end=120".into(),
+            "blowing_bc.Constant.slot-ned=130".into(),
+        ],
+    };
+    let error = Config::load(&dir.join("cases/fast.yaml"), &nested_typo)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("blowing_bc.Constant.slot_ned"), "{error}");
+
+    // a base that extends the config extending it
+    fs::write(dir.join("a.json"), r#"{"extends": "b.json"}"#).unwrap();
+    fs::write(dir.join("b.json"), r#"{"extends": "a.json"}"#).unwrap();
+    assert!(Config::load(&dir.join("a.json"), &Default::default()).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,119 @@
+//! inspect the inflow boundary layer database `database_bl.dat`
+//!
+//! the database is a plain text table of numbers with one row for every point of the y grid,
+//! where the first column is the wall normal coordinate. The meaning of the other columns is
+//! defined by the reader of the solver, so every column is summarized by its index.
+
+use crate::prelude::*;
+
+/// number of rows drawn in the profile plot of `summarize`
+const PLOT_ROWS: usize = 24;
+/// width of the bar of the largest value in the profile plot
+const PLOT_WIDTH: usize = 50;
+
+pub(crate) fn database_bl(args: cli::DatabaseBl) -> anyhow::Result<()> {
+    match args.mode {
+        cli::DatabaseBlMode::Summarize(args) => summarize(args),
+    }
+}
+
+fn summarize(args: cli::SummarizeDatabaseBl) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(&args.database)
+        .with_context(|| format!("failed to read database at {}", args.database.display()))?;
+
+    let rows = parse_database(&contents)
+        .with_context(|| format!("failed to parse database at {}", args.database.display()))?;
+
+    let columns = rows[0].len();
+    anyhow::ensure!(
+        args.column < columns,
+        "column {} was requested, but the database only has {columns} columns",
+        args.column
+    );
+
+    println!(
+        "{}: {} rows, {columns} columns",
+        args.database.display(),
+        rows.len()
+    );
+
+    for column in 0..columns {
+        let (min, max) = rows
+            .iter()
+            .map(|row| row[column])
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+        println!("    column {column}: min {min:.6e} max {max:.6e}");
+    }
+
+    // the first column is the wall normal coordinate
+    let edge = rows.last().unwrap()[args.column];
+    if let Some(row) = rows.iter().find(|row| row[args.column] >= 0.99 * edge) {
+        println!(
+            "column {} reaches 99% of its last value at y = {:.4}",
+            args.column, row[0]
+        );
+    }
+
+    println!();
+    println!("column {} against y:", args.column);
+
+    let largest = rows
+        .iter()
+        .map(|row| row[args.column].abs())
+        .fold(0., f64::max);
+    let stride = rows.len().div_ceil(PLOT_ROWS);
+
+    for row in rows.iter().step_by(stride) {
+        let value = row[args.column];
+        let width = if largest > 0. {
+            (value.abs() / largest * PLOT_WIDTH as f64).round() as usize
+        } else {
+            0
+        };
+        println!("{:>10.4} {:>12.4e} |{}", row[0], value, "#".repeat(width));
+    }
+
+    Ok(())
+}
+
+/// every row of numbers in a database. Rows must all have the same number of columns
+fn parse_database(contents: &str) -> anyhow::Result<Vec<Vec<f64>>> {
+    let rows = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            line.split_whitespace()
+                .map(|value| {
+                    // fortran may write exponents with a d
+                    value.replace(['d', 'D'], "e").parse::<f64>()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("line {} is not a row of numbers: {line}", idx + 1))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    anyhow::ensure!(!rows.is_empty(), "the database is empty");
+
+    let columns = rows[0].len();
+    if let Some(idx) = rows.iter().position(|row| row.len() != columns) {
+        anyhow::bail!(
+            "row {} has {} columns, but the first row has {columns}",
+            idx + 1,
+            rows[idx].len()
+        );
+    }
+
+    Ok(rows)
+}
+
+#[test]
+fn parse_fortran_database() {
+    let rows = parse_database("0.0 0.0 1.0\n\n 0.5D0 0.8d0  1.0E0\n").unwrap();
+    assert_eq!(rows, [[0., 0., 1.], [0.5, 0.8, 1.]]);
+
+    assert!(parse_database("0.0 0.0\n0.5\n").is_err());
+    assert!(parse_database("y u\n0.0 0.0\n").is_err());
+}
@@ -0,0 +1,252 @@
+//! suggest MPI decompositions of a grid
+//!
+//! the solver splits the domain along x between `mpi_x_split` ranks (one GPU each), which
+//! requires both `x_divisions` and `y_divisions` to be divisible by the split. Every legal split
+//! is ranked by how much memory each rank needs and how much of each rank is ghost points, and
+//! nearby grid sizes are suggested when a desired split is not legal. The split in z is fixed.
+
+use crate::config_generator::{gpu_memory_per_rank, Megabytes, GHOST_POINTS};
+use crate::prelude::*;
+
+/// number of nearby grids suggested for a split that is not legal
+const MAX_NEARBY_GRIDS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Grid {
+    pub(crate) x_divisions: usize,
+    pub(crate) y_divisions: usize,
+    pub(crate) z_divisions: usize,
+}
+
+impl Grid {
+    fn is_legal_split(&self, split: usize) -> bool {
+        split > 0 && self.x_divisions % split == 0 && self.y_divisions % split == 0
+    }
+}
+
+/// a legal `mpi_x_split` of a grid
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+    pub(crate) split: usize,
+    pub(crate) memory_per_rank: Megabytes,
+    /// fraction of the points on each rank that are not ghost points
+    pub(crate) efficiency: f64,
+    /// whether the rank fits in the memory of the GPU, if the memory is known
+    pub(crate) fits: bool,
+}
+
+/// every legal split with no more than `max_ranks` ranks in x, best first. Splits that fit in
+/// GPU memory come first, then splits with less memory per rank, then splits with fewer ghost
+/// points
+pub(crate) fn candidates(
+    grid: Grid,
+    mpi_z_split: usize,
+    max_ranks: Option<usize>,
+    gpu_memory: Option<Megabytes>,
+) -> Vec<Candidate> {
+    let rank_z = grid.z_divisions / mpi_z_split.max(1);
+
+    let max_ranks = max_ranks.unwrap_or(grid.x_divisions);
+
+    let mut candidates = (1..=max_ranks.min(grid.x_divisions))
+        .filter(|split| grid.is_legal_split(*split))
+        .map(|split| {
+            let memory_per_rank = gpu_memory_per_rank(
+                grid.x_divisions,
+                grid.y_divisions,
+                grid.z_divisions,
+                split,
+                mpi_z_split,
+            );
+
+            let rank_x = grid.x_divisions / split;
+            let interior = (rank_x * grid.y_divisions * rank_z) as f64;
+            let padded = ((rank_x + 2 * GHOST_POINTS)
+                * (grid.y_divisions + 2 * GHOST_POINTS)
+                * (rank_z + 2 * GHOST_POINTS)) as f64;
+
+            Candidate {
+                split,
+                memory_per_rank,
+                efficiency: interior / padded,
+                fits: gpu_memory
+                    .map(|memory| memory_per_rank <= memory)
+                    .unwrap_or(true),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort_by(|a, b| {
+        b.fits
+            .cmp(&a.fits)
+            .then(a.memory_per_rank.0.cmp(&b.memory_per_rank.0))
+            .then(b.efficiency.total_cmp(&a.efficiency))
+    });
+
+    candidates
+}
+
+/// grids close to `grid` that can be legally split between `split` ranks, closest first
+pub(crate) fn nearby_grids(grid: Grid, split: usize) -> Vec<Grid> {
+    if split == 0 {
+        return Vec::new();
+    }
+
+    // the closest multiples of the split below and above a number of divisions
+    let multiples = |divisions: usize| {
+        let below = divisions / split * split;
+        let above = divisions.div_ceil(split) * split;
+        let mut multiples = vec![below, above];
+        multiples.retain(|value| *value > 0);
+        multiples.dedup();
+        multiples
+    };
+
+    let mut grids = Vec::new();
+
+    for x_divisions in multiples(grid.x_divisions) {
+        for y_divisions in multiples(grid.y_divisions) {
+            grids.push(Grid {
+                x_divisions,
+                y_divisions,
+                z_divisions: grid.z_divisions,
+            });
+        }
+    }
+
+    let distance = |other: &Grid| {
+        grid.x_divisions.abs_diff(other.x_divisions) + grid.y_divisions.abs_diff(other.y_divisions)
+    };
+    grids.sort_by_key(distance);
+    grids.truncate(MAX_NEARBY_GRIDS);
+
+    grids
+}
+
+/// the best legal split of a config's grid that uses at most `mpi_x_split` ranks and fits in
+/// GPU memory
+pub(crate) fn auto_split(config: &Config, gpu_memory: Option<Megabytes>) -> anyhow::Result<usize> {
+    let grid = Grid {
+        x_divisions: config.x_divisions,
+        y_divisions: config.y_divisions,
+        z_divisions: config.z_divisions,
+    };
+
+    let best = candidates(
+        grid,
+        config.mpi_z_split,
+        Some(config.mpi_x_split),
+        gpu_memory,
+    )
+    .into_iter()
+    .find(|candidate| candidate.fits);
+
+    match best {
+        Some(candidate) => Ok(candidate.split),
+        None => anyhow::bail!(
+            "no split of {} x {} x {} between at most {} ranks fits in gpu memory. Run \
+             `decompose` to see the memory needed by each split",
+            grid.x_divisions,
+            grid.y_divisions,
+            grid.z_divisions,
+            config.mpi_x_split
+        ),
+    }
+}
+
+pub(crate) fn decompose(args: cli::Decompose) -> anyhow::Result<()> {
+    let gpu_memory = crate::gpu::selected_gpu_memory(&args.gpu)?;
+
+    let grid = Grid {
+        x_divisions: args.x_divisions,
+        y_divisions: args.y_divisions,
+        z_divisions: args.z_divisions,
+    };
+
+    let candidates = candidates(grid, args.mpi_z_split, args.gpus, gpu_memory);
+
+    println!(
+        "legal splits of {} x {} x {}, best first:",
+        grid.x_divisions, grid.y_divisions, grid.z_divisions
+    );
+    println!(
+        "    {:>6} {:>16} {:>12} {:>6}",
+        "split", "memory per rank", "efficiency", "fits"
+    );
+    for candidate in &candidates {
+        println!(
+            "    {:>6} {:>16} {:>11.1}% {:>6}",
+            candidate.split,
+            candidate.memory_per_rank.to_string(),
+            candidate.efficiency * 100.,
+            if candidate.fits { "yes" } else { "no" }
+        );
+    }
+
+    if let Some(split) = args.split.or(args.gpus) {
+        if !grid.is_legal_split(split) {
+            println!("\n{split} ranks cannot split this grid. Nearby grids that can be split:");
+            for nearby in nearby_grids(grid, split) {
+                let memory = gpu_memory_per_rank(
+                    nearby.x_divisions,
+                    nearby.y_divisions,
+                    nearby.z_divisions,
+                    split,
+                    args.mpi_z_split,
+                );
+                println!(
+                    "    {} x {} x {} ({memory} per rank)",
+                    nearby.x_divisions, nearby.y_divisions, nearby.z_divisions
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn splits_divide_both_x_and_y() {
+    let grid = Grid {
+        x_divisions: 800,
+        y_divisions: 208,
+        z_divisions: 150,
+    };
+
+    let mut splits = candidates(grid, 1, None, None)
+        .iter()
+        .map(|candidate| candidate.split)
+        .collect::<Vec<_>>();
+    splits.sort_unstable();
+    assert_eq!(splits, [1, 2, 4, 8, 16]);
+
+    // with 6 gpus the best split uses 4 of them, and fewer ranks need too much memory
+    let ranked = candidates(grid, 1, Some(6), Some(Megabytes(3000)));
+    assert_eq!(ranked[0].split, 4);
+    let fitting = ranked
+        .iter()
+        .filter(|candidate| candidate.fits)
+        .map(|candidate| candidate.split)
+        .collect::<Vec<_>>();
+    assert_eq!(fitting, [4]);
+}
+
+#[test]
+fn nearby_grids_are_legal() {
+    let grid = Grid {
+        x_divisions: 800,
+        y_divisions: 208,
+        z_divisions: 150,
+    };
+
+    let nearby = nearby_grids(grid, 6);
+    assert_eq!(
+        nearby[0],
+        Grid {
+            x_divisions: 798,
+            y_divisions: 210,
+            z_divisions: 150
+        }
+    );
+    assert!(nearby.iter().all(|grid| grid.is_legal_split(6)));
+}
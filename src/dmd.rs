@@ -0,0 +1,701 @@
+//! dynamic mode decomposition (DMD) of span average snapshots, with and without control
+//!
+//! every decomposition here is computed from the `num_snapshots x num_snapshots` inner products
+//! of the snapshots (in the same way as the method of snapshots for POD), so the `4 * nx * ny`
+//! length of a snapshot only appears when the results are projected back to full fields.
+
+use crate::actuation::ActuationHistory;
+use crate::prelude::*;
+use crate::snapshots;
+use crate::snapshots::SpanSnapshots;
+use crate::snapshots::SNAPSHOT_FIELDS;
+
+use nalgebra::Complex;
+use nalgebra::DMatrix;
+use nalgebra::DVector;
+use ndarray::s;
+use vtk::{Scalar2D, Vector2D};
+
+type CMatrix = DMatrix<Complex<f64>>;
+
+#[derive(vtk::DataArray, Clone)]
+/// real and imaginary parts of a single DMD mode
+struct DmdModeVtkInformation {
+    rho_real: Scalar2D<f64>,
+    velocity_real: Vector2D<f64>,
+    rho_imag: Scalar2D<f64>,
+    velocity_imag: Vector2D<f64>,
+}
+
+/// spectrum of the decomposition, written to dmd.mat. Modes are sorted by amplitude.
+#[derive(mat5::MatFile)]
+struct DmdResults {
+    /// real part of the discrete time eigenvalues (over one snapshot interval)
+    eigenvalues_real: Vec<f64>,
+    /// imaginary part of the discrete time eigenvalues (over one snapshot interval)
+    eigenvalues_imag: Vec<f64>,
+    /// frequency of each mode in cycles per (nondimensional) unit time
+    frequency: Vec<f64>,
+    /// exponential growth rate of each mode per (nondimensional) unit time
+    growth_rate: Vec<f64>,
+    /// magnitude of each mode in the first snapshot
+    amplitude: Vec<f64>,
+    /// singular values of the snapshot matrix that was truncated
+    singular_values: Vec<f64>,
+    /// solver step of each snapshot
+    steps: Vec<f64>,
+    /// physical time of each snapshot
+    time: Vec<f64>,
+    /// jet amplitude at each snapshot. Empty unless DMDc was run
+    actuation: Vec<f64>,
+    /// `(4 * nx * ny, 1)` identified input matrix. Empty unless DMDc was run
+    b_matrix: Array2,
+}
+
+pub(crate) fn dmd(args: cli::Dmd) -> anyhow::Result<()> {
+    let config_path = args.solver_results.join("input.json");
+    let config = Config::load(&config_path, &args.overrides)
+        .with_context(|| format!("failed to read config at path {}", config_path.display()))?;
+
+    let mesh = run::MeshInfo::from_base_path(&args.solver_results, &config)?;
+
+    let snapshots = SpanSnapshots::from_solver_results(&args.solver_results, &config)?;
+
+    if snapshots.num_snapshots() < 3 {
+        anyhow::bail!(
+            "at least three span averages are required for DMD, found {}",
+            snapshots.num_snapshots()
+        );
+    }
+
+    let dt = config.timestep(args.dt)?;
+    let time = snapshots
+        .steps
+        .iter()
+        .map(|step| *step as f64 * dt)
+        .collect::<Vec<_>>();
+    let relative_time = time.iter().map(|t| t - time[0]).collect::<Vec<_>>();
+
+    println!("computing snapshot inner products");
+    let gram = gram_matrix(snapshots.matrix());
+
+    let mut actuation = Vec::new();
+
+    let decomposition = match args.method {
+        cli::DmdMethod::Exact => {
+            let interval = uniform_interval(&relative_time)?;
+            exact_dmd(&gram, args.rank, interval)
+        }
+        cli::DmdMethod::Optimized => optimized_dmd(&gram, &relative_time, args.rank),
+        cli::DmdMethod::Dmdc => {
+            let interval = uniform_interval(&relative_time)?;
+
+            let history = if let Some(log) = &args.actuation_log {
+                ActuationHistory::from_csv(log)?
+            } else {
+                ActuationHistory::from_config(&config, dt, &snapshots.steps)?
+            };
+
+            actuation = snapshots
+                .steps
+                .iter()
+                .map(|step| history.amplitude_at(*step))
+                .collect();
+
+            let inputs = DMatrix::from_row_slice(1, actuation.len(), &actuation);
+            let input_rank = args.input_rank.unwrap_or(args.rank + 1);
+            dmdc(&gram, &inputs, args.rank, input_rank, interval)
+        }
+    };
+
+    let mut decomposition = decomposition;
+    let amplitude = decomposition.normalize(&gram);
+
+    // sort the modes with the largest contribution to the first snapshot first
+    let mut order = (0..amplitude.len()).collect::<Vec<_>>();
+    order.sort_unstable_by(|&a, &b| amplitude[b].total_cmp(&amplitude[a]));
+
+    //
+    // write the results
+    //
+
+    if !args.output_directory.exists() {
+        fs::create_dir_all(&args.output_directory).with_context(|| {
+            format!(
+                "failed to create output directory {}",
+                args.output_directory.display()
+            )
+        })?;
+    }
+
+    let domain = snapshots::span_domain(&mesh, &config);
+    let field_shape = (SNAPSHOT_FIELDS, config.x_divisions, config.y_divisions);
+
+    let modes_real = project(snapshots.matrix(), &decomposition.modes.map(|x| x.re));
+    let modes_imag = project(snapshots.matrix(), &decomposition.modes.map(|x| x.im));
+
+    for (output_idx, &mode) in order.iter().enumerate() {
+        let real = modes_real
+            .slice(s![mode, ..])
+            .to_owned()
+            .into_shape(field_shape)
+            .expect("mode has the same number of points as a snapshot");
+        let imag = modes_imag
+            .slice(s![mode, ..])
+            .to_owned()
+            .into_shape(field_shape)
+            .expect("mode has the same number of points as a snapshot");
+
+        let data = DmdModeVtkInformation {
+            rho_real: Scalar2D::new(real.slice(s![0usize, .., ..]).to_owned()),
+            velocity_real: Vector2D::new(real.slice(s![1usize..4, .., ..]).to_owned()),
+            rho_imag: Scalar2D::new(imag.slice(s![0usize, .., ..]).to_owned()),
+            velocity_imag: Vector2D::new(imag.slice(s![1usize..4, .., ..]).to_owned()),
+        };
+
+        let path = args
+            .output_directory
+            .join(format!("dmd_mode_{output_idx:03}.vtr"));
+        let writer = io::BufWriter::new(
+            fs::File::create(&path)
+                .with_context(|| format!("failed to create {}", path.display()))?,
+        );
+        vtk::write_vtk(writer, vtk::VtkData::new(domain.clone(), data))?;
+    }
+
+    let b_matrix = if let Some(b_coefficients) = &decomposition.b_coefficients {
+        let b_matrix = project(snapshots.matrix(), b_coefficients);
+
+        for (input, column) in b_matrix.outer_iter().enumerate() {
+            let field = column
+                .to_owned()
+                .into_shape(field_shape)
+                .expect("B has the same number of points as a snapshot");
+
+            snapshots::write_snapshot_vtk(
+                &args
+                    .output_directory
+                    .join(format!("dmdc_b_matrix_{input:03}.vtr")),
+                domain.clone(),
+                field,
+            )?;
+        }
+
+        b_matrix.reversed_axes()
+    } else {
+        Array2::zeros((0, 0))
+    };
+
+    let eigenvalues = order
+        .iter()
+        .map(|&mode| (decomposition.omega[mode] * decomposition.interval).exp())
+        .collect::<Vec<_>>();
+
+    let results = DmdResults {
+        eigenvalues_real: eigenvalues.iter().map(|x| x.re).collect(),
+        eigenvalues_imag: eigenvalues.iter().map(|x| x.im).collect(),
+        frequency: order
+            .iter()
+            .map(|&mode| decomposition.omega[mode].im / (2. * std::f64::consts::PI))
+            .collect(),
+        growth_rate: order
+            .iter()
+            .map(|&mode| decomposition.omega[mode].re)
+            .collect(),
+        amplitude: order.iter().map(|&mode| amplitude[mode]).collect(),
+        singular_values: decomposition.singular_values,
+        steps: snapshots.steps.iter().map(|step| *step as f64).collect(),
+        time,
+        actuation,
+        b_matrix,
+    };
+
+    let mat_path = args.output_directory.join("dmd.mat");
+    let writer = io::BufWriter::new(
+        fs::File::create(&mat_path)
+            .with_context(|| format!("failed to create {}", mat_path.display()))?,
+    );
+    mat5::MatFile::write_contents(&results, writer)?;
+
+    Ok(())
+}
+
+/// the result of a decomposition, expressed as linear combinations of the snapshots
+struct Decomposition {
+    /// continuous time eigenvalues
+    omega: Vec<Complex<f64>>,
+    /// time between snapshots used to convert `omega` to discrete time eigenvalues
+    interval: f64,
+    /// `(num_snapshots, num_modes)` coefficients: mode `j` is `sum_i modes[(i, j)] * snapshot_i`
+    modes: CMatrix,
+    /// amplitudes of each mode if they were found in the fit, otherwise they are computed
+    /// from the first snapshot
+    amplitudes: Option<Vec<f64>>,
+    singular_values: Vec<f64>,
+    /// `(num_snapshots, num_inputs)` coefficients of the input matrix, for DMDc
+    b_coefficients: Option<DMatrix<f64>>,
+}
+
+impl Decomposition {
+    /// scale every mode to unit norm and return the amplitude of each mode
+    fn normalize(&mut self, gram: &DMatrix<f64>) -> Vec<f64> {
+        let gram = gram.map(|x| Complex::new(x, 0.));
+
+        let norms = (0..self.modes.ncols())
+            .map(|j| {
+                let column = self.modes.column(j);
+                (column.adjoint() * &gram * column)[(0, 0)]
+                    .re
+                    .max(0.)
+                    .sqrt()
+            })
+            .collect::<Vec<_>>();
+
+        for (j, norm) in norms.iter().enumerate() {
+            if *norm > 0. {
+                self.modes.column_mut(j).scale_mut(1. / norm);
+            }
+        }
+
+        if let Some(amplitudes) = &self.amplitudes {
+            return amplitudes
+                .iter()
+                .zip(&norms)
+                .map(|(amplitude, norm)| amplitude * norm)
+                .collect();
+        }
+
+        // least squares fit of the normalized modes to the first snapshot:
+        // (modes^H modes) b = modes^H x_0
+        let lhs = self.modes.adjoint() * &gram * &self.modes;
+        let rhs = self.modes.adjoint() * gram.column(0);
+
+        lhs.svd(true, true)
+            .solve(&rhs, 1e-12)
+            .map(|b| b.iter().map(|x| x.norm()).collect())
+            .unwrap_or_else(|_| vec![0.; norms.len()])
+    }
+}
+
+/// inner products of every pair of rows of a `(num_snapshots, num_points)` matrix
+fn gram_matrix(snapshots: ndarray::ArrayView2<f64>) -> DMatrix<f64> {
+    let gram = snapshots.dot(&snapshots.t());
+    let n = gram.nrows();
+
+    DMatrix::from_fn(n, n, |i, j| 0.5 * (gram[[i, j]] + gram[[j, i]]))
+}
+
+/// evaluate `coefficients^T * snapshots`, the full fields of each column of coefficients
+fn project(snapshots: ndarray::ArrayView2<f64>, coefficients: &DMatrix<f64>) -> Array2 {
+    let coefficients =
+        Array2::from_shape_fn((coefficients.ncols(), coefficients.nrows()), |(j, i)| {
+            coefficients[(i, j)]
+        });
+
+    coefficients.dot(&snapshots)
+}
+
+/// ensure that snapshots are evenly spaced in time, returning the interval between them
+fn uniform_interval(time: &[f64]) -> anyhow::Result<f64> {
+    let interval = (time[time.len() - 1] - time[0]) / (time.len() - 1) as f64;
+
+    for window in time.windows(2) {
+        let difference = window[1] - window[0];
+        if (difference - interval).abs() > 1e-6 * interval.abs() {
+            anyhow::bail!(
+                "snapshots are not evenly spaced in time (found an interval of {difference} where {interval} was expected). Use `--method optimized` for unevenly spaced snapshots"
+            );
+        }
+    }
+
+    Ok(interval)
+}
+
+/// truncated right singular vectors and singular values of a matrix from its gram matrix
+fn gram_svd(gram: &DMatrix<f64>, rank: usize) -> (DMatrix<f64>, Vec<f64>) {
+    let eigen = nalgebra::SymmetricEigen::new(gram.clone());
+
+    let mut order = (0..gram.nrows()).collect::<Vec<_>>();
+    order.sort_unstable_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+
+    // discard directions that are only round off
+    let threshold = eigen.eigenvalues[order[0]].max(0.) * 1e-12;
+    let order = order
+        .into_iter()
+        .take(rank)
+        .take_while(|idx| eigen.eigenvalues[*idx] > threshold)
+        .collect::<Vec<_>>();
+
+    let vectors = DMatrix::from_fn(gram.nrows(), order.len(), |i, j| {
+        eigen.eigenvectors[(i, order[j])]
+    });
+    let singular_values = order
+        .iter()
+        .map(|idx| eigen.eigenvalues[*idx].sqrt())
+        .collect();
+
+    (vectors, singular_values)
+}
+
+/// `matrix * diag(1 / values)`
+fn scale_columns(matrix: &DMatrix<f64>, values: &[f64]) -> DMatrix<f64> {
+    let mut matrix = matrix.clone();
+    for (j, value) in values.iter().enumerate() {
+        matrix.column_mut(j).scale_mut(1. / value);
+    }
+    matrix
+}
+
+/// eigenvalues and eigenvectors of a general real matrix
+///
+/// eigenvectors are found by inverse iteration on each of the eigenvalues
+fn eig(matrix: &DMatrix<f64>) -> (Vec<Complex<f64>>, CMatrix) {
+    let n = matrix.nrows();
+    let eigenvalues = matrix
+        .complex_eigenvalues()
+        .iter()
+        .copied()
+        .collect::<Vec<_>>();
+
+    let complex = matrix.map(|x| Complex::new(x, 0.));
+    let perturbation = complex.norm().max(f64::MIN_POSITIVE) * 1e-10;
+
+    let mut vectors = CMatrix::zeros(n, n);
+
+    for (col, eigenvalue) in eigenvalues.iter().enumerate() {
+        // a small shift keeps the system from being exactly singular
+        let shift = eigenvalue + Complex::new(perturbation, perturbation);
+        let shifted = &complex - CMatrix::identity(n, n) * shift;
+        let lu = shifted.lu();
+
+        let mut vector =
+            DVector::from_fn(n, |i, _| Complex::new(1. + i as f64 / n as f64, 0.)).normalize();
+
+        for _ in 0..3 {
+            if let Some(next) = lu.solve(&vector) {
+                vector = next.normalize();
+            }
+        }
+
+        vectors.set_column(col, &vector);
+    }
+
+    (eigenvalues, vectors)
+}
+
+/// continuous time eigenvalues from discrete time eigenvalues over a given interval
+fn continuous_eigenvalues(eigenvalues: &[Complex<f64>], interval: f64) -> Vec<Complex<f64>> {
+    eigenvalues.iter().map(|x| x.ln() / interval).collect()
+}
+
+/// exact DMD (Tu et al. 2014) of evenly spaced snapshots
+fn exact_dmd(gram: &DMatrix<f64>, rank: usize, interval: f64) -> Decomposition {
+    let m = gram.nrows();
+
+    // inner products of X = snapshots[0..m-1] and Y = snapshots[1..m]
+    let kxx = gram.view((0, 0), (m - 1, m - 1)).into_owned();
+    let kxy = gram.view((0, 1), (m - 1, m - 1)).into_owned();
+
+    let (v, sigma) = gram_svd(&kxx, rank);
+    let v_sigma_inv = scale_columns(&v, &sigma);
+
+    // A_tilde = U^T Y V S^-1 where U = X V S^-1
+    let a_tilde = v_sigma_inv.transpose() * &kxy * &v_sigma_inv;
+    let (eigenvalues, w) = eig(&a_tilde);
+
+    // modes = Y V S^-1 W
+    let coefficients = v_sigma_inv.map(|x| Complex::new(x, 0.)) * w;
+    let mut modes = CMatrix::zeros(m, coefficients.ncols());
+    modes
+        .view_mut((1, 0), (m - 1, coefficients.ncols()))
+        .copy_from(&coefficients);
+
+    Decomposition {
+        omega: continuous_eigenvalues(&eigenvalues, interval),
+        interval,
+        modes,
+        amplitudes: None,
+        singular_values: sigma,
+        b_coefficients: None,
+    }
+}
+
+/// DMD with control (Proctor et al. 2016) of evenly spaced snapshots, where `inputs` is a
+/// `(num_inputs, num_snapshots)` matrix of the actuation at each snapshot
+fn dmdc(
+    gram: &DMatrix<f64>,
+    inputs: &DMatrix<f64>,
+    rank: usize,
+    input_rank: usize,
+    interval: f64,
+) -> Decomposition {
+    let m = gram.nrows();
+
+    let kxx = gram.view((0, 0), (m - 1, m - 1)).into_owned();
+    let kxy = gram.view((0, 1), (m - 1, m - 1)).into_owned();
+    let kyy = gram.view((1, 1), (m - 1, m - 1)).into_owned();
+    let upsilon = inputs.columns(0, m - 1).into_owned();
+
+    // truncated svd of the stacked matrix [X; Upsilon]
+    let omega_gram = &kxx + upsilon.transpose() * &upsilon;
+    let (vp, sigma_p) = gram_svd(&omega_gram, input_rank);
+    let vp_sigma_inv = scale_columns(&vp, &sigma_p);
+
+    // truncated svd of the output space Y
+    let (vr, sigma_r) = gram_svd(&kyy, rank);
+    let vr_sigma_inv = scale_columns(&vr, &sigma_r);
+
+    // U_1^T U_hat
+    let u1_u_hat = vp_sigma_inv.transpose() * &kxy * &vr_sigma_inv;
+    // U_hat^T Y V_p S_p^-1
+    let g = vr_sigma_inv.transpose() * &kyy * &vp_sigma_inv;
+    // U_2 = Upsilon V_p S_p^-1
+    let u2 = &upsilon * &vp_sigma_inv;
+
+    let a_tilde = &g * &u1_u_hat;
+    let b_tilde = &g * u2.transpose();
+
+    let (eigenvalues, w) = eig(&a_tilde);
+
+    // modes = Y V_p S_p^-1 U_1^T U_hat W
+    let coefficients = (&vp_sigma_inv * &u1_u_hat).map(|x| Complex::new(x, 0.)) * w;
+    let mut modes = CMatrix::zeros(m, coefficients.ncols());
+    modes
+        .view_mut((1, 0), (m - 1, coefficients.ncols()))
+        .copy_from(&coefficients);
+
+    // B = U_hat B_tilde
+    let b = &vr_sigma_inv * b_tilde;
+    let mut b_coefficients = DMatrix::zeros(m, b.ncols());
+    b_coefficients
+        .view_mut((1, 0), (m - 1, b.ncols()))
+        .copy_from(&b);
+
+    Decomposition {
+        omega: continuous_eigenvalues(&eigenvalues, interval),
+        interval,
+        modes,
+        amplitudes: None,
+        singular_values: sigma_p,
+        b_coefficients: Some(b_coefficients),
+    }
+}
+
+/// optimized DMD (Askham & Kutz 2018): the continuous time eigenvalues and modes are fit to all
+/// of the (possibly unevenly spaced) snapshots at once with variable projection.
+///
+/// The snapshots are first projected onto their leading POD modes, and the fit is started from
+/// the exact DMD eigenvalues.
+fn optimized_dmd(gram: &DMatrix<f64>, time: &[f64], rank: usize) -> Decomposition {
+    let m = gram.nrows();
+    let interval = time[m - 1] / (m - 1) as f64;
+
+    let (v, sigma) = gram_svd(gram, rank);
+    let v_sigma_inv = scale_columns(&v, &sigma);
+
+    // coordinates of each snapshot on the POD basis U = snapshots^T V S^-1, one row per snapshot
+    let reduced = (v_sigma_inv.transpose() * gram)
+        .transpose()
+        .map(|x| Complex::new(x, 0.));
+
+    let initial = exact_dmd(gram, rank, interval).omega;
+    let mut parameters = DVector::zeros(2 * initial.len());
+    for (j, omega) in initial.iter().enumerate() {
+        parameters[2 * j] = omega.re;
+        parameters[2 * j + 1] = omega.im;
+    }
+
+    let fit = |parameters: &DVector<f64>| -> (CMatrix, CMatrix) {
+        let num_modes = parameters.len() / 2;
+        let phi = CMatrix::from_fn(m, num_modes, |i, j| {
+            (Complex::new(parameters[2 * j], parameters[2 * j + 1]) * time[i]).exp()
+        });
+
+        let b = phi
+            .clone()
+            .svd(true, true)
+            .solve(&reduced, 1e-12)
+            .unwrap_or_else(|_| CMatrix::zeros(num_modes, reduced.ncols()));
+
+        (phi, b)
+    };
+
+    let residual = |parameters: &DVector<f64>| -> DVector<f64> {
+        let (phi, b) = fit(parameters);
+        let difference = &reduced - phi * b;
+
+        DVector::from_iterator(
+            2 * difference.len(),
+            difference.iter().flat_map(|x| [x.re, x.im]),
+        )
+    };
+
+    let parameters = levenberg_marquardt(residual, parameters, 100);
+    let (_, b) = fit(&parameters);
+
+    let omega = (0..parameters.len() / 2)
+        .map(|j| Complex::new(parameters[2 * j], parameters[2 * j + 1]))
+        .collect();
+
+    // mode j, scaled by its amplitude, is U b_j^T
+    let modes = v_sigma_inv.map(|x| Complex::new(x, 0.)) * b.transpose();
+
+    Decomposition {
+        omega,
+        interval,
+        modes,
+        // the amplitude is already contained in the norm of each mode
+        amplitudes: Some(vec![1.; b.nrows()]),
+        singular_values: sigma,
+        b_coefficients: None,
+    }
+}
+
+/// minimize the norm of a residual function with a Levenberg-Marquardt iteration using a
+/// finite difference jacobian
+fn levenberg_marquardt<F>(residual: F, initial: DVector<f64>, max_iterations: usize) -> DVector<f64>
+where
+    F: Fn(&DVector<f64>) -> DVector<f64>,
+{
+    let mut parameters = initial;
+    let mut current = residual(&parameters);
+    let mut cost = current.norm_squared();
+    let mut damping = 1e-3;
+
+    for _ in 0..max_iterations {
+        let mut jacobian = DMatrix::zeros(current.len(), parameters.len());
+        for k in 0..parameters.len() {
+            let step = 1e-7 * parameters[k].abs().max(1.);
+            let mut perturbed = parameters.clone();
+            perturbed[k] += step;
+            let column = (residual(&perturbed) - &current) / step;
+            jacobian.set_column(k, &column);
+        }
+
+        let jtj = jacobian.transpose() * &jacobian;
+        let gradient = jacobian.transpose() * &current;
+
+        let mut improved = false;
+
+        for _ in 0..10 {
+            let mut lhs = jtj.clone();
+            for k in 0..lhs.nrows() {
+                lhs[(k, k)] += damping * jtj[(k, k)].max(1e-12);
+            }
+
+            let delta = match lhs.lu().solve(&(-&gradient)) {
+                Some(delta) => delta,
+                None => {
+                    damping *= 4.;
+                    continue;
+                }
+            };
+
+            let candidate = &parameters + delta;
+            let candidate_residual = residual(&candidate);
+            let candidate_cost = candidate_residual.norm_squared();
+
+            if candidate_cost < cost {
+                let relative_change = (cost - candidate_cost) / cost.max(f64::MIN_POSITIVE);
+
+                parameters = candidate;
+                current = candidate_residual;
+                cost = candidate_cost;
+                damping = (damping / 3.).max(1e-12);
+                improved = relative_change > 1e-12;
+                break;
+            }
+
+            damping *= 4.;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    parameters
+}
+
+#[cfg(test)]
+/// snapshots of two travelling waves with known frequencies and growth rates
+fn travelling_wave_snapshots(time: &[f64]) -> Array2 {
+    let num_points = 64;
+
+    Array2::from_shape_fn((time.len(), num_points), |(t, i)| {
+        let x = 2. * std::f64::consts::PI * i as f64 / num_points as f64;
+        let t = time[t];
+        (-0.1 * t).exp() * (x - 2. * t).cos() + 0.5 * (0.05 * t).exp() * (2. * x + 5. * t).sin()
+    })
+}
+
+#[cfg(test)]
+fn has_eigenvalue(omega: &[Complex<f64>], expected: Complex<f64>) -> bool {
+    omega.iter().any(|x| (x - expected).norm() < 1e-4)
+}
+
+#[test]
+/// exact and optimized DMD should recover the eigenvalues of two travelling waves
+fn dmd_recovers_travelling_waves() {
+    let time = (0..40).map(|t| t as f64 * 0.1).collect::<Vec<_>>();
+    let snapshots = travelling_wave_snapshots(&time);
+    let gram = gram_matrix(snapshots.view());
+
+    let expected = [
+        Complex::new(-0.1, 2.),
+        Complex::new(-0.1, -2.),
+        Complex::new(0.05, 5.),
+        Complex::new(0.05, -5.),
+    ];
+
+    let exact = exact_dmd(&gram, 4, 0.1);
+    for eigenvalue in expected {
+        assert!(has_eigenvalue(&exact.omega, eigenvalue));
+    }
+
+    // optimized DMD does not need evenly spaced snapshots
+    let uneven_time = (0..40)
+        .map(|t| t as f64 * 0.1 + 0.02 * (t as f64).sin())
+        .collect::<Vec<_>>();
+    let snapshots = travelling_wave_snapshots(&uneven_time);
+    let gram = gram_matrix(snapshots.view());
+
+    let optimized = optimized_dmd(&gram, &uneven_time, 4);
+    for eigenvalue in expected {
+        assert!(has_eigenvalue(&optimized.omega, eigenvalue));
+    }
+}
+
+#[test]
+/// DMDc should recover the dynamics and input matrix of a linear system
+fn dmdc_recovers_linear_system() {
+    let a = DMatrix::from_row_slice(3, 3, &[0.9, 0.1, 0., -0.1, 0.9, 0., 0., 0., 0.5]);
+    let b = DVector::from_column_slice(&[0., 1., 0.5]);
+
+    let num_snapshots = 30;
+    let inputs = DMatrix::from_fn(1, num_snapshots, |_, k| (0.7 * k as f64).sin());
+
+    let mut state = DVector::from_column_slice(&[1., 0., 1.]);
+    let mut snapshots = Array2::zeros((num_snapshots, 3));
+    for k in 0..num_snapshots {
+        for i in 0..3 {
+            snapshots[[k, i]] = state[i];
+        }
+        state = &a * &state + &b * inputs[(0, k)];
+    }
+
+    let gram = gram_matrix(snapshots.view());
+    let decomposition = dmdc(&gram, &inputs, 3, 4, 1.);
+
+    for eigenvalue in a.complex_eigenvalues().iter() {
+        assert!(has_eigenvalue(&decomposition.omega, eigenvalue.ln()));
+    }
+
+    let identified = project(
+        snapshots.view(),
+        decomposition.b_coefficients.as_ref().unwrap(),
+    );
+    for i in 0..3 {
+        assert!((identified[[0, i]] - b[i]).abs() < 1e-8);
+    }
+}
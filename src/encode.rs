@@ -0,0 +1,295 @@
+//! encode the `anim_XXXXX.png` frames written by `animate` to a video
+//!
+//! mp4 and webm videos are encoded by a local ffmpeg. Gifs are encoded by ffmpeg if it is
+//! installed and otherwise in rust, as are animated pngs, so that a preview can always be made.
+
+use crate::prelude::*;
+use cli::VideoFormat;
+use xshell::{cmd, Shell};
+
+/// encode every frame in `frame_folder` to `output`
+pub(crate) fn encode_frames(
+    frame_folder: &Path,
+    output: &Path,
+    format: VideoFormat,
+    framerate: u32,
+    quality: u8,
+) -> anyhow::Result<()> {
+    if framerate == 0 {
+        anyhow::bail!("the framerate must be at least 1");
+    }
+
+    let frames = frame_files(frame_folder)?;
+
+    if frames.is_empty() {
+        anyhow::bail!("no animation frames found in {}", frame_folder.display());
+    }
+
+    let sh = Shell::new()?;
+    let has_ffmpeg = cmd!(sh, "ffmpeg -version")
+        .quiet()
+        .ignore_stdout()
+        .ignore_stderr()
+        .run()
+        .is_ok();
+
+    println!("encoding {} frames to {}", frames.len(), output.display());
+
+    match format {
+        VideoFormat::Mp4 | VideoFormat::Webm if !has_ffmpeg => anyhow::bail!(
+            "ffmpeg was not found on the PATH, and is required to encode {} videos. Install \
+             ffmpeg or encode to gif or apng instead",
+            format.extension()
+        ),
+        VideoFormat::Mp4 | VideoFormat::Webm => {
+            ffmpeg(&sh, frame_folder, output, format, framerate, quality)?
+        }
+        VideoFormat::Gif if has_ffmpeg => {
+            ffmpeg(&sh, frame_folder, output, format, framerate, quality)?
+        }
+        VideoFormat::Gif => {
+            println!("ffmpeg was not found, encoding the gif without it");
+            encode_gif(&frames, output, framerate, quality)?
+        }
+        VideoFormat::Apng => encode_apng(&frames, output, framerate)?,
+    }
+
+    println!("wrote {}", output.display());
+
+    Ok(())
+}
+
+/// the frames in a folder, in the order they are played
+fn frame_files(frame_folder: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut frames = fs::read_dir(frame_folder)
+        .with_context(|| format!("failed to read frame folder {}", frame_folder.display()))?
+        .filter_map(|entry_res| entry_res.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| {
+                    let name = name.to_string_lossy();
+                    name.starts_with("anim_") && name.ends_with(".png")
+                })
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+
+    // frame numbers are zero padded so sorting them lexically sorts them in order
+    frames.sort_unstable();
+
+    Ok(frames)
+}
+
+/// map a quality from 0 (worst) to 100 (best) linearly onto a range of encoder settings
+fn scale_quality(quality: u8, worst: u32, best: u32) -> u32 {
+    let fraction = quality.min(100) as f64 / 100.;
+    (worst as f64 + (best as f64 - worst as f64) * fraction).round() as u32
+}
+
+fn ffmpeg(
+    sh: &Shell,
+    frame_folder: &Path,
+    output: &Path,
+    format: VideoFormat,
+    framerate: u32,
+    quality: u8,
+) -> anyhow::Result<()> {
+    let input = frame_folder.join("anim_%05d.png");
+    let framerate = framerate.to_string();
+
+    let codec_args: Vec<String> = match format {
+        VideoFormat::Mp4 => vec![
+            "-c:v".into(),
+            "libx264".into(),
+            "-crf".into(),
+            scale_quality(quality, 40, 10).to_string(),
+            "-pix_fmt".into(),
+            "yuv420p".into(),
+            // yuv420p requires even dimensions
+            "-vf".into(),
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2".into(),
+        ],
+        VideoFormat::Webm => vec![
+            "-c:v".into(),
+            "libvpx-vp9".into(),
+            "-crf".into(),
+            scale_quality(quality, 55, 15).to_string(),
+            "-b:v".into(),
+            "0".into(),
+            "-pix_fmt".into(),
+            "yuv420p".into(),
+            "-vf".into(),
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2".into(),
+        ],
+        VideoFormat::Gif => {
+            // a palette generated from the frames looks far better than the default one
+            let colors = scale_quality(quality, 16, 256);
+            vec![
+                "-vf".into(),
+                format!("split[a][b];[a]palettegen=max_colors={colors}[p];[b][p]paletteuse"),
+            ]
+        }
+        VideoFormat::Apng => unreachable!("animated pngs are always encoded in rust"),
+    };
+
+    cmd!(
+        sh,
+        "ffmpeg -y -loglevel error -framerate {framerate} -i {input} {codec_args...} {output}"
+    )
+    .run()
+    .with_context(|| format!("ffmpeg failed to encode {}", output.display()))?;
+
+    Ok(())
+}
+
+/// an RGB frame read back from disk
+struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn read_frame(path: &Path) -> anyhow::Result<Frame> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    let mut decoder = png::Decoder::new(io::BufReader::new(file));
+    // expands palettes and bit depths below 8 so that every frame decodes to 8 bit color
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder
+        .read_info()
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut pixels)
+        .with_context(|| format!("failed to decode {}", path.display()))?;
+    pixels.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgb => pixels,
+        png::ColorType::Rgba => pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|value| [*value; 3]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0]; 3])
+            .collect(),
+        png::ColorType::Indexed => {
+            anyhow::bail!("{} was not expanded from a palette", path.display())
+        }
+    };
+
+    Ok(Frame {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+/// check that every frame after the first has the same size
+fn check_size(first: &Frame, frame: &Frame, path: &Path) -> anyhow::Result<()> {
+    if (first.width, first.height) != (frame.width, frame.height) {
+        anyhow::bail!(
+            "{} is {}x{} but the first frame is {}x{}",
+            path.display(),
+            frame.width,
+            frame.height,
+            first.width,
+            first.height
+        );
+    }
+
+    Ok(())
+}
+
+/// encode an animated gif, quantizing every frame to its own 256 color palette
+fn encode_gif(
+    frames: &[PathBuf],
+    output: &Path,
+    framerate: u32,
+    quality: u8,
+) -> anyhow::Result<()> {
+    let first = read_frame(&frames[0])?;
+
+    let (width, height) = match (u16::try_from(first.width), u16::try_from(first.height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => anyhow::bail!("frames are too large to be encoded to a gif"),
+    };
+
+    let file = fs::File::create(output)
+        .with_context(|| format!("failed to create {}", output.display()))?;
+    let mut encoder = gif::Encoder::new(io::BufWriter::new(file), width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    // gif delays are in hundredths of a second
+    let delay = (100. / framerate as f64).round().max(1.) as u16;
+    // 1 is the slowest and best quantization, 30 the fastest
+    let speed = 30 - scale_quality(quality, 0, 29) as i32;
+
+    for (idx, path) in frames.iter().enumerate() {
+        // the first frame was already read to find the size of the gif
+        let frame = if idx == 0 {
+            None
+        } else {
+            let frame = read_frame(path)?;
+            check_size(&first, &frame, path)?;
+            Some(frame)
+        };
+        let pixels = &frame.as_ref().unwrap_or(&first).pixels;
+
+        let mut gif_frame = gif::Frame::from_rgb_speed(width, height, pixels, speed);
+        gif_frame.delay = delay;
+        encoder
+            .write_frame(&gif_frame)
+            .with_context(|| format!("failed to write {} to the gif", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// encode an animated png, which is lossless
+fn encode_apng(frames: &[PathBuf], output: &Path, framerate: u32) -> anyhow::Result<()> {
+    let first = read_frame(&frames[0])?;
+
+    let file = fs::File::create(output)
+        .with_context(|| format!("failed to create {}", output.display()))?;
+
+    let mut encoder = png::Encoder::new(io::BufWriter::new(file), first.width, first.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // a play count of 0 loops forever
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(1, framerate.min(u16::MAX as u32) as u16)?;
+
+    let mut writer = encoder.write_header()?;
+
+    for (idx, path) in frames.iter().enumerate() {
+        // the first frame was already read to find the size of the image
+        let frame = if idx == 0 {
+            None
+        } else {
+            let frame = read_frame(path)?;
+            check_size(&first, &frame, path)?;
+            Some(frame)
+        };
+
+        writer
+            .write_image_data(&frame.as_ref().unwrap_or(&first).pixels)
+            .with_context(|| format!("failed to write {} to the animated png", path.display()))?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[test]
+fn quality_maps_onto_encoder_range() {
+    assert_eq!(scale_quality(0, 40, 10), 40);
+    assert_eq!(scale_quality(100, 40, 10), 10);
+    assert_eq!(scale_quality(50, 16, 256), 136);
+}
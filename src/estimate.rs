@@ -0,0 +1,354 @@
+//! estimate the resources a planned run needs before it is submitted
+//!
+//! GPU memory follows the solver's own check (see [`Config::gpu_memory_per_rank`]), disk usage
+//! is computed from the size of every file the solver and postprocessing write, and wall time
+//! from the throughput of the selected GPU profile (see [`crate::gpu`]).
+
+use crate::config_generator::{Megabytes, GHOST_POINTS, VTK_SAVE_INTERVAL};
+use crate::gpu::GpuProfile;
+use crate::prelude::*;
+
+/// number of conserved variables (rho, rho u, rho v, rho w, rho E)
+const NUM_VARIABLES: u64 = 5;
+
+/// rough number of 3D arrays (including ghost points) that every rank keeps in host memory
+const HOST_ARRAYS: u64 = 16;
+
+const BYTES_PER_F64: u64 = 8;
+const BYTES_PER_F32: u64 = 4;
+
+pub(crate) fn estimate(args: cli::Estimate) -> anyhow::Result<()> {
+    let config = Config::load(&args.config, &args.overrides)
+        .with_context(|| format!("failed to read config at {}", args.config.display()))?;
+
+    let profile = crate::gpu::selected_profile(&args.gpu)?;
+    let dt = config.timestep(args.dt).ok();
+
+    let estimate = ResourceEstimate::new(&config, profile.as_ref(), dt);
+    estimate.print(&config, profile.as_ref());
+
+    let exceeded = estimate.exceeded_quotas(&args, profile.as_ref());
+
+    if !exceeded.is_empty() {
+        println!();
+        for quota in &exceeded {
+            println!("EXCEEDED: {quota}");
+        }
+        anyhow::bail!("the run exceeds {} of its quotas", exceeded.len());
+    }
+
+    Ok(())
+}
+
+/// a kind of file written during a run, and how much is written
+#[derive(Debug)]
+struct OutputStream {
+    name: &'static str,
+    /// number of times data is written, or `None` if it could not be determined
+    writes: Option<u64>,
+    /// size of the data written each time
+    bytes_per_write: u64,
+}
+
+impl OutputStream {
+    fn total_bytes(&self) -> u64 {
+        self.writes.unwrap_or(0) * self.bytes_per_write
+    }
+}
+
+#[derive(Debug)]
+struct ResourceEstimate {
+    gpu_memory_per_rank: Megabytes,
+    /// host memory used by every rank together
+    host_memory_bytes: u64,
+    outputs: Vec<OutputStream>,
+    /// `None` if the GPU has no known throughput
+    wall_time_hours: Option<f64>,
+}
+
+impl ResourceEstimate {
+    fn new(config: &Config, profile: Option<&GpuProfile>, dt: Option<f64>) -> Self {
+        let x_ranks = config.mpi_x_split.max(1) as u64;
+        let z_ranks = config.mpi_z_split.max(1) as u64;
+        let ranks = x_ranks * z_ranks;
+        let nx = config.x_divisions as u64;
+        let ny = config.y_divisions as u64;
+        let nz = config.z_divisions as u64;
+        let steps = config.steps as u64;
+
+        let ghost_points = GHOST_POINTS as u64;
+        let padded_rank_points = (nx / x_ranks + 2 * ghost_points)
+            * (ny + 2 * ghost_points)
+            * (nz / z_ranks + 2 * ghost_points);
+        let host_memory_bytes = HOST_ARRAYS * padded_rank_points * BYTES_PER_F64 * ranks;
+
+        // a write every `interval` steps, where 0 disables the output
+        let writes = |interval: usize| {
+            if interval == 0 {
+                0
+            } else {
+                steps / interval as u64
+            }
+        };
+
+        let span_writes = writes(config.span_average_io_steps);
+        let plane_bytes = NUM_VARIABLES * nx * ny * BYTES_PER_F64;
+        let volume_points = nx * ny * nz;
+
+        let flowfield_writes = match config.python_flowfield_steps {
+            Some(interval) if config.use_python => writes(interval),
+            _ => 0,
+        };
+
+        let vtk_writes = if config.snapshots_3d {
+            dt.map(|dt| (steps as f64 * dt / VTK_SAVE_INTERVAL).floor() as u64)
+        } else {
+            Some(0)
+        };
+
+        let outputs = vec![
+            OutputStream {
+                name: "span average binaries",
+                writes: Some(span_writes),
+                // every process along z writes the average over its own part of the span
+                bytes_per_write: plane_bytes * z_ranks,
+            },
+            OutputStream {
+                name: "probe binaries",
+                writes: Some(writes(config.probe_io_steps)),
+                // 4 variables at 3 heights across the span, for every probe
+                bytes_per_write: config.probe_locations_x.len() as u64 * 3 * 4 * nz * BYTES_PER_F64,
+            },
+            OutputStream {
+                name: "hdf5 flowfields",
+                writes: Some(flowfield_writes),
+                bytes_per_write: NUM_VARIABLES * volume_points * BYTES_PER_F64,
+            },
+            OutputStream {
+                name: "3D vtk snapshots",
+                writes: vtk_writes,
+                bytes_per_write: NUM_VARIABLES * volume_points * BYTES_PER_F64,
+            },
+            OutputStream {
+                name: "span average vtks (postprocessed)",
+                writes: Some(span_writes),
+                bytes_per_write: plane_bytes + (nx + ny) * BYTES_PER_F64,
+            },
+            OutputStream {
+                name: "flowfield vtks (hdf5-to-vtk)",
+                writes: Some(flowfield_writes),
+                bytes_per_write: NUM_VARIABLES * volume_points * BYTES_PER_F32
+                    + (nx + ny + nz) * BYTES_PER_F32,
+            },
+        ];
+
+        // every rank advances its own part of the domain at the same time
+        let wall_time_hours =
+            profile
+                .and_then(|profile| profile.throughput_mpts)
+                .map(|throughput| {
+                    let rank_points = (nx / x_ranks) * ny * (nz / z_ranks);
+                    steps as f64 * rank_points as f64 / (throughput * 1e6) / 3600.
+                });
+
+        Self {
+            gpu_memory_per_rank: config.gpu_memory_per_rank(),
+            host_memory_bytes,
+            outputs,
+            wall_time_hours,
+        }
+    }
+
+    /// disk space used by every output. Span binaries are removed once they are converted, so
+    /// this is an upper bound
+    fn disk_bytes(&self) -> u64 {
+        self.outputs.iter().map(OutputStream::total_bytes).sum()
+    }
+
+    fn print(&self, config: &Config, profile: Option<&GpuProfile>) {
+        let ranks = config.nproc().max(1);
+
+        println!(
+            "domain: {} x {} x {} points on {} x {} mpi ranks (one gpu each)",
+            config.x_divisions,
+            config.y_divisions,
+            config.z_divisions,
+            config.mpi_x_split,
+            config.mpi_z_split
+        );
+
+        match profile {
+            Some(profile) => println!(
+                "gpu memory per rank: {} of {}",
+                self.gpu_memory_per_rank,
+                Megabytes(profile.memory_mb)
+            ),
+            None => println!("gpu memory per rank: {}", self.gpu_memory_per_rank),
+        }
+
+        println!(
+            "host memory: {} ({} per rank)",
+            format_bytes(self.host_memory_bytes),
+            format_bytes(self.host_memory_bytes / ranks as u64)
+        );
+
+        println!("disk usage:");
+        for output in &self.outputs {
+            match output.writes {
+                Some(writes) => println!(
+                    "    {:<36} {:>6} x {:>10} = {}",
+                    output.name,
+                    writes,
+                    format_bytes(output.bytes_per_write),
+                    format_bytes(output.total_bytes())
+                ),
+                None => println!(
+                    "    {:<36} unknown, the config has no fixed_dt so the timestep must be passed with --dt",
+                    output.name
+                ),
+            }
+        }
+        println!("    {:<36} {}", "total", format_bytes(self.disk_bytes()));
+
+        match self.wall_time_hours {
+            Some(hours) => println!("wall time: {hours:.2} hours for {} steps", config.steps),
+            None => println!("wall time: unknown, the selected gpu has no throughput"),
+        }
+    }
+
+    /// a description of every quota the run would exceed
+    fn exceeded_quotas(&self, args: &cli::Estimate, profile: Option<&GpuProfile>) -> Vec<String> {
+        let mut exceeded = Vec::new();
+
+        if let Some(profile) = profile {
+            let memory = Megabytes(profile.memory_mb);
+            if self.gpu_memory_per_rank > memory {
+                exceeded.push(format!(
+                    "gpu memory: {} required per rank, {} available",
+                    self.gpu_memory_per_rank, memory
+                ));
+            }
+        }
+
+        let gigabytes = |quota: f64| (quota * 1e9) as u64;
+
+        if let Some(quota) = args.memory_quota {
+            if self.host_memory_bytes > gigabytes(quota) {
+                exceeded.push(format!(
+                    "host memory: {} required, {} available",
+                    format_bytes(self.host_memory_bytes),
+                    format_bytes(gigabytes(quota))
+                ));
+            }
+        }
+
+        if let Some(quota) = args.disk_quota {
+            if self.disk_bytes() > gigabytes(quota) {
+                exceeded.push(format!(
+                    "disk: {} required, {} available",
+                    format_bytes(self.disk_bytes()),
+                    format_bytes(gigabytes(quota))
+                ));
+            }
+        }
+
+        if let (Some(limit), Some(hours)) = (args.time_limit, self.wall_time_hours) {
+            if hours > limit {
+                exceeded.push(format!(
+                    "wall time: {hours:.2} hours required, limit is {limit} hours"
+                ));
+            }
+        }
+
+        exceeded
+    }
+}
+
+/// a byte count in the largest (decimal) unit that keeps it above 1
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1000. && unit < UNITS.len() - 1 {
+        value /= 1000.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+#[test]
+fn output_streams_follow_io_steps() {
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.steps = 1000;
+    config.span_average_io_steps = 100;
+    config.probe_io_steps = 0;
+    config.snapshots_3d = true;
+
+    let estimate = ResourceEstimate::new(&config, None, Some(0.01));
+
+    let writes = |name: &str| {
+        estimate
+            .outputs
+            .iter()
+            .find(|output| output.name == name)
+            .unwrap()
+            .writes
+    };
+
+    assert_eq!(writes("span average binaries"), Some(10));
+    assert_eq!(writes("probe binaries"), Some(0));
+    // python flowfields are never written by the fortran solver
+    assert_eq!(writes("hdf5 flowfields"), Some(0));
+    // 1000 steps of 0.01 is 10 time units, with a snapshot every 5
+    assert_eq!(writes("3D vtk snapshots"), Some(2));
+    assert!(estimate.wall_time_hours.is_none());
+
+    let estimate = ResourceEstimate::new(&config, None, None);
+    assert_eq!(
+        estimate
+            .outputs
+            .iter()
+            .find(|output| output.name == "3D vtk snapshots")
+            .unwrap()
+            .writes,
+        None
+    );
+}
+
+#[test]
+fn span_binaries_hold_every_z_process() {
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.mpi_z_split = 1;
+
+    let bytes = |config: &Config, name: &str| {
+        ResourceEstimate::new(config, None, None)
+            .outputs
+            .into_iter()
+            .find(|output| output.name == name)
+            .unwrap()
+            .bytes_per_write
+    };
+
+    let binary = bytes(&config, "span average binaries");
+    let vtk = bytes(&config, "span average vtks (postprocessed)");
+
+    config.mpi_z_split = 4;
+
+    assert_eq!(bytes(&config, "span average binaries"), 4 * binary);
+    // the z processes are averaged together before the vtk is written
+    assert_eq!(bytes(&config, "span average vtks (postprocessed)"), vtk);
+}
+
+#[test]
+fn bytes_are_formatted_in_decimal_units() {
+    assert_eq!(format_bytes(999), "999 B");
+    assert_eq!(format_bytes(1_500_000), "1.50 MB");
+    assert_eq!(format_bytes(2_000_000_000_000), "2.00 TB");
+}
@@ -0,0 +1,185 @@
+//! memory capacities of the GPUs that configs are validated against
+//!
+//! a handful of common GPUs are built in, and more can be added in a TOML file of the form
+//!
+//! ```toml
+//! [gpu.a40]
+//! memory_mb = 48000
+//! throughput_mpts = 300
+//! description = "NVIDIA A40 48 GB"
+//! ```
+//!
+//! which is read from `--gpu-profiles`, or `~/.config/streams-utils/gpus.toml` if it exists.
+//! Profiles in the file replace built in profiles of the same name.
+
+use crate::config_generator::Megabytes;
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+/// the GPU configs are validated against when none is selected: the 2080 Ti in the lab
+pub(crate) const DEFAULT_GPU: &str = "2080ti";
+
+/// (name, memory in megabytes, throughput in millions of grid points per second, description)
+/// of the built in profiles. Throughputs are rough figures for the solver in double precision
+const BUILTIN_PROFILES: &[(&str, usize, f64, &str)] = &[
+    ("2080ti", 11_000, 150., "NVIDIA GeForce RTX 2080 Ti 11 GB"),
+    ("3090", 24_000, 220., "NVIDIA GeForce RTX 3090 24 GB"),
+    ("4090", 24_000, 300., "NVIDIA GeForce RTX 4090 24 GB"),
+    ("t4", 16_000, 70., "NVIDIA T4 16 GB"),
+    ("v100-16gb", 16_000, 250., "NVIDIA V100 16 GB"),
+    ("v100-32gb", 32_000, 250., "NVIDIA V100 32 GB"),
+    ("a6000", 48_000, 280., "NVIDIA RTX A6000 48 GB"),
+    ("a100-40gb", 40_000, 450., "NVIDIA A100 40 GB"),
+    ("a100-80gb", 80_000, 500., "NVIDIA A100 80 GB"),
+    ("h100-80gb", 80_000, 750., "NVIDIA H100 80 GB"),
+];
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct GpuProfile {
+    /// memory available on a single GPU
+    pub(crate) memory_mb: usize,
+    /// millions of grid points a single GPU advances by one step every second
+    #[serde(default)]
+    pub(crate) throughput_mpts: Option<f64>,
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+}
+
+/// layout of a user GPU profile file
+#[derive(Debug, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    gpu: BTreeMap<String, GpuProfile>,
+}
+
+/// every GPU profile that can be selected by name
+pub(crate) struct GpuRegistry {
+    profiles: BTreeMap<String, GpuProfile>,
+}
+
+impl GpuRegistry {
+    pub(crate) fn builtin() -> Self {
+        let profiles = BUILTIN_PROFILES
+            .iter()
+            .map(|(name, memory_mb, throughput_mpts, description)| {
+                let profile = GpuProfile {
+                    memory_mb: *memory_mb,
+                    throughput_mpts: Some(*throughput_mpts),
+                    description: Some(description.to_string()),
+                };
+                (name.to_string(), profile)
+            })
+            .collect();
+
+        Self { profiles }
+    }
+
+    /// the built in profiles, along with the profiles of a user file. If no file is given, the
+    /// default location is read if it exists
+    pub(crate) fn load(user_profiles: Option<&Path>) -> anyhow::Result<Self> {
+        let mut registry = Self::builtin();
+
+        let path = match user_profiles {
+            Some(path) => Some(path.to_owned()),
+            None => default_profile_path().filter(|path| path.exists()),
+        };
+
+        if let Some(path) = path {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read gpu profiles at {}", path.display()))?;
+            registry
+                .extend_from_toml(&contents)
+                .with_context(|| format!("failed to parse gpu profiles at {}", path.display()))?;
+        }
+
+        Ok(registry)
+    }
+
+    fn extend_from_toml(&mut self, contents: &str) -> anyhow::Result<()> {
+        let file: ProfileFile = toml::from_str(contents)?;
+
+        for (name, profile) in file.gpu {
+            self.profiles.insert(name.to_lowercase(), profile);
+        }
+
+        Ok(())
+    }
+
+    /// look up a profile by (case insensitive) name
+    pub(crate) fn get(&self, name: &str) -> anyhow::Result<&GpuProfile> {
+        self.profiles.get(&name.to_lowercase()).with_context(|| {
+            format!(
+                "unknown gpu `{name}`. Known gpus are: {}",
+                self.profiles
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+    }
+}
+
+fn default_profile_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(
+        PathBuf::from(home)
+            .join(".config")
+            .join("streams-utils")
+            .join("gpus.toml"),
+    )
+}
+
+/// the profile of the GPU selected on the command line, or `None` if the memory should not be
+/// checked. A GPU given only by its memory has no known throughput
+pub(crate) fn selected_profile(args: &cli::GpuSelection) -> anyhow::Result<Option<GpuProfile>> {
+    if args.no_gpu_check {
+        return Ok(None);
+    }
+
+    if let Some(memory_mb) = args.gpu_memory {
+        return Ok(Some(GpuProfile {
+            memory_mb,
+            throughput_mpts: None,
+            description: None,
+        }));
+    }
+
+    let registry = GpuRegistry::load(args.gpu_profiles.as_deref())?;
+    let name = args.gpu.as_deref().unwrap_or(DEFAULT_GPU);
+    let profile = registry.get(name)?;
+
+    Ok(Some(profile.clone()))
+}
+
+/// the memory of a single GPU that a config should be validated against, or `None` if the
+/// memory should not be checked
+pub(crate) fn selected_gpu_memory(args: &cli::GpuSelection) -> anyhow::Result<Option<Megabytes>> {
+    let profile = selected_profile(args)?;
+    Ok(profile.map(|profile| Megabytes(profile.memory_mb)))
+}
+
+#[test]
+fn user_profiles_extend_builtins() {
+    let mut registry = GpuRegistry::builtin();
+    registry
+        .extend_from_toml(
+            r#"
+            [gpu.A40]
+            memory_mb = 48000
+            throughput_mpts = 300
+
+            [gpu.2080ti]
+            memory_mb = 10000
+            description = "a smaller card"
+            "#,
+        )
+        .unwrap();
+
+    assert_eq!(registry.get("a40").unwrap().memory_mb, 48000);
+    assert_eq!(registry.get("a40").unwrap().throughput_mpts, Some(300.));
+    assert_eq!(registry.get("2080TI").unwrap().memory_mb, 10000);
+    assert_eq!(registry.get("2080ti").unwrap().throughput_mpts, None);
+    assert_eq!(registry.get("a100-80gb").unwrap().memory_mb, 80000);
+    assert!(registry.get("not-a-gpu").is_err());
+}
@@ -68,7 +68,7 @@ pub(crate) fn hdf5_to_vtk(args: cli::HDF5ToVtk) -> Result<()> {
     //
 
     let config_path = args.solver_results.join("input.json");
-    let config = Config::from_path(&config_path)
+    let config = Config::load(&config_path, &args.overrides)
         .with_context(|| format!("failed to read config at path {}", config_path.display()))?;
 
     let mesh = run::MeshInfo::from_base_path(&args.solver_results, &config)?;
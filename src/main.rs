@@ -1,14 +1,32 @@
+mod actuation;
 mod animate;
 mod binary_to_vtk;
 mod cli;
 mod config_generator;
+mod config_resolve;
+mod database_bl;
+mod decompose;
+mod dmd;
+mod encode;
+mod estimate;
+mod gpu;
 mod hdf5_to_vtk;
+mod mat73;
+mod mesh;
+mod npy;
+mod pod;
 mod prelude;
 mod probe;
 mod probe_binary;
+mod probe_output;
+mod probe_spectra;
+mod render;
 mod run;
+mod snapshots;
+mod spans_to_hdf5;
 mod spans_to_vtk;
 mod utils;
+mod validate;
 mod vtk_to_mat;
 
 use prelude::*;
@@ -22,12 +40,22 @@ fn main() -> anyhow::Result<()> {
 
     match args.mode {
         Command::ConfigGenerator(x) => config_generator::config_generator(x)?,
+        Command::Validate(x) => validate::validate(x)?,
+        Command::ConfigResolve(x) => config_resolve::config_resolve(x)?,
+        Command::Estimate(x) => estimate::estimate(x)?,
+        Command::DatabaseBl(x) => database_bl::database_bl(x)?,
+        Command::Decompose(x) => decompose::decompose(x)?,
         Command::RunContainer(x) => run::run_container(x)?,
         Command::RunLocal(x) => run::run_local(x)?,
         Command::Probe(x) => probe::probe(x)?,
+        Command::ProbeSpectra(x) => probe_spectra::probe_spectra(x)?,
         Command::VtkToMat(x) => vtk_to_mat::vtk_to_mat(x)?,
         Command::SpansToVtk(x) => spans_to_vtk::spans_to_vtk(x)?,
         Command::HDF5ToVtk(x) => hdf5_to_vtk::hdf5_to_vtk(x)?,
+        Command::SpansToHdf5(x) => spans_to_hdf5::spans_to_hdf5(x)?,
+        Command::Pod(x) => pod::pod(x)?,
+        Command::Dmd(x) => dmd::dmd(x)?,
+        Command::ActuationHistory(x) => actuation::actuation_history(x)?,
         Command::Animate(x) => animate::animate(x)?,
     };
 
@@ -45,13 +73,32 @@ enum Error {
     #[error("{0}")]
     SerializationJson(serde_json::Error),
     #[error("{0}")]
-    BinaryVtkError(binary_to_vtk::BinaryToVtkError),
+    SerializationToml(toml::de::Error),
+    #[error("{0}")]
+    ConfigResolve(config_resolve::ResolveError),
+    #[error("{0}")]
+    BinaryVtkError(binary_to_vtk::SpanBinaryError),
     #[error("{0}")]
     Vtk(vtk::Error),
     #[error("{0}")]
     ProbeBinary(probe_binary::ProbeBinaryError),
     #[error("Could not write the file using mat5: {0}")]
     Mat5(mat5::Error),
+    #[error("Could not create a thread pool: {0}")]
+    ThreadPool(rayon::ThreadPoolBuildError),
+    #[error("{0}")]
+    Hdf5(hdf5::Error),
+    #[error("{0}")]
+    Hdf5String(hdf5::types::StringError),
+    #[error("Could not write the zip archive: {0}")]
+    Zip(zip::result::ZipError),
+    #[cfg(feature = "netcdf")]
+    #[error("{0}")]
+    Netcdf(netcdf::error::Error),
+    #[cfg(not(feature = "netcdf"))]
+    #[from(ignore)]
+    #[error("NetCDF output requires streams-utils to be built with the `netcdf` feature")]
+    NetcdfUnsupported,
 }
 
 #[derive(Display, Debug, Constructor)]
@@ -0,0 +1,234 @@
+//! writing arrays to HDF5 files, optionally laid out as MATLAB v7.3 .mat files
+//!
+//! a v7.3 .mat file is an HDF5 file with a 512 byte userblock holding the usual MATLAB header,
+//! and a `MATLAB_class` attribute on every variable. MATLAB reads HDF5 datasets in column major
+//! order, so arrays are stored with their axes reversed for MATLAB to see the same shape as the
+//! arrays written by `mat5`.
+
+use crate::prelude::*;
+use hdf5::types::{FixedAscii, VarLenUnicode};
+use ndarray::{s, Ix2, Ix3};
+
+/// size of the userblock that MATLAB expects before the HDF5 data
+const USERBLOCK_SIZE: u64 = 512;
+
+/// an HDF5 (or MATLAB v7.3) file that arrays are written to
+pub(crate) struct Hdf5ArrayWriter {
+    path: PathBuf,
+    file: hdf5::File,
+    matlab: bool,
+}
+
+impl Hdf5ArrayWriter {
+    /// create a plain HDF5 file, or a MATLAB v7.3 file if `matlab` is set
+    pub(crate) fn create(path: &Path, matlab: bool) -> anyhow::Result<Self> {
+        let file = if matlab {
+            hdf5::File::with_options()
+                .with_fcpl(|fcpl| fcpl.userblock(USERBLOCK_SIZE))
+                .create(path)
+        } else {
+            hdf5::File::create(path)
+        }
+        .with_context(|| format!("failed to create hdf5 file {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            matlab,
+        })
+    }
+
+    /// write a complete array as the variable `name`
+    pub(crate) fn write_array<D: ndarray::Dimension>(
+        &self,
+        name: &str,
+        array: ndarray::ArrayView<'_, f64, D>,
+    ) -> anyhow::Result<()> {
+        let array = if self.matlab {
+            array.reversed_axes().as_standard_layout().into_owned()
+        } else {
+            array.as_standard_layout().into_owned()
+        };
+
+        let dataset = self
+            .file
+            .new_dataset_builder()
+            .with_data(&array)
+            .create(name)
+            .with_context(|| format!("failed to write `{name}` to {}", self.path.display()))?;
+
+        self.set_matlab_class(&dataset, "double")?;
+
+        Ok(())
+    }
+
+    /// create a chunked variable `name` of `num_snapshots` snapshots, each shaped
+    /// `snapshot_shape`, that is filled one snapshot at a time
+    pub(crate) fn snapshot_array(
+        &self,
+        name: &str,
+        num_snapshots: usize,
+        snapshot_shape: &[usize],
+    ) -> anyhow::Result<SnapshotDataset> {
+        let mut shape = vec![num_snapshots];
+        shape.extend_from_slice(snapshot_shape);
+        let mut chunk = vec![1];
+        chunk.extend_from_slice(snapshot_shape);
+
+        if self.matlab {
+            shape.reverse();
+            chunk.reverse();
+        }
+
+        let dataset = self
+            .file
+            .new_dataset::<f64>()
+            .shape(shape)
+            .chunk(ndarray::IxDyn(&chunk))
+            .deflate(utils::COMPRESSION_LEVEL)
+            .create(name)
+            .with_context(|| format!("failed to create `{name}` in {}", self.path.display()))?;
+
+        self.set_matlab_class(&dataset, "double")?;
+
+        Ok(SnapshotDataset {
+            name: name.to_string(),
+            dataset,
+            matlab: self.matlab,
+        })
+    }
+
+    /// write a string as the variable `name`. MATLAB files store it as a char array, and plain
+    /// HDF5 files as a scalar string dataset
+    pub(crate) fn write_string(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        let context = || format!("failed to write `{name}` to {}", self.path.display());
+
+        if self.matlab {
+            // a 1 x n char array, stored (like every other variable) with its axes reversed
+            let chars = value.encode_utf16().collect::<Vec<u16>>();
+            let chars = ndarray::Array2::from_shape_vec((chars.len(), 1), chars)
+                .expect("shape matches the number of characters");
+
+            let dataset = self
+                .file
+                .new_dataset_builder()
+                .with_data(&chars)
+                .create(name)
+                .with_context(context)?;
+
+            self.set_matlab_class(&dataset, "char")?;
+        } else {
+            let value: VarLenUnicode = value.parse()?;
+            self.file
+                .new_dataset::<VarLenUnicode>()
+                .shape(())
+                .create(name)
+                .with_context(context)?
+                .write_scalar(&value)
+                .with_context(context)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_matlab_class(&self, dataset: &hdf5::Dataset, class: &str) -> anyhow::Result<()> {
+        if !self.matlab {
+            return Ok(());
+        }
+
+        // the longest class name written is `double`, shorter names are null padded
+        let class = FixedAscii::<6>::from_ascii(class)?;
+        dataset
+            .new_attr::<FixedAscii<6>>()
+            .shape(())
+            .create("MATLAB_class")?
+            .write_scalar(&class)?;
+
+        Ok(())
+    }
+
+    /// close the file, writing the MATLAB header into the userblock if required
+    pub(crate) fn finish(self) -> anyhow::Result<()> {
+        let Self { path, file, matlab } = self;
+
+        // the hdf5 file must be closed before the userblock is written
+        drop(file);
+
+        if matlab {
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .with_context(|| format!("failed to open {} to write header", path.display()))?;
+
+            file.write_all(&matlab_header())
+                .with_context(|| format!("failed to write header to {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// a variable of an [`Hdf5ArrayWriter`] that is written one snapshot at a time
+pub(crate) struct SnapshotDataset {
+    name: String,
+    dataset: hdf5::Dataset,
+    matlab: bool,
+}
+
+impl SnapshotDataset {
+    /// write the snapshot at index `idx`
+    pub(crate) fn write<D: ndarray::Dimension>(
+        &self,
+        idx: usize,
+        snapshot: ndarray::ArrayView<'_, f64, D>,
+    ) -> anyhow::Result<()> {
+        let snapshot = if self.matlab {
+            snapshot.reversed_axes().as_standard_layout().into_owned()
+        } else {
+            snapshot.as_standard_layout().into_owned()
+        }
+        .into_dyn();
+
+        let result = match (snapshot.ndim(), self.matlab) {
+            (2, false) => self
+                .dataset
+                .write_slice(&snapshot.into_dimensionality::<Ix2>()?, s![idx, .., ..]),
+            (2, true) => self
+                .dataset
+                .write_slice(&snapshot.into_dimensionality::<Ix2>()?, s![.., .., idx]),
+            (3, false) => self
+                .dataset
+                .write_slice(&snapshot.into_dimensionality::<Ix3>()?, s![idx, .., .., ..]),
+            (3, true) => self
+                .dataset
+                .write_slice(&snapshot.into_dimensionality::<Ix3>()?, s![.., .., .., idx]),
+            (ndim, _) => anyhow::bail!("{ndim} dimensional snapshots are not supported"),
+        };
+
+        result.with_context(|| format!("failed to write snapshot {idx} of `{}`", self.name))
+    }
+}
+
+/// the 128 byte header at the start of a v7.3 .mat file
+fn matlab_header() -> [u8; 128] {
+    let mut header = [b' '; 128];
+
+    let text =
+        "MATLAB 7.3 MAT-file, Platform: GLNXA64, Created by: streams-utils HDF5 schema 1.00 .";
+    header[..text.len()].copy_from_slice(text.as_bytes());
+
+    // subsystem data offset (unused)
+    header[116..124].fill(0);
+    // version 0x0200, and the endian indicator
+    header[124..126].copy_from_slice(&0x0200u16.to_le_bytes());
+    header[126..128].copy_from_slice(b"IM");
+
+    header
+}
+
+#[test]
+fn matlab_header_layout() {
+    let header = matlab_header();
+    assert!(header.starts_with(b"MATLAB 7.3 MAT-file"));
+    assert_eq!(&header[124..], &[0x00, 0x02, b'I', b'M']);
+}
@@ -0,0 +1,288 @@
+//! preview the mesh the solver will generate for a config
+//!
+//! [`run::MeshInfo`] reads `x.dat`, `y.dat` and `z.dat` after a run. This module approximates the
+//! grid generation of the solver for boundary layer flows so that the mesh can be checked before
+//! a run is submitted. x and z are uniform. In y, the first `nymax_wr` points are assumed to form
+//! the wall resolved region between the wall and `rly_wr`, stretched with
+//!
+//! ```text
+//! y_j = rly_wr * sinh(alpha * j / (nymax_wr - 1)) / sinh(alpha)
+//! ```
+//!
+//! where `alpha` is chosen so that the first cell is `dy+_w` wall units tall. The remaining points
+//! grow in a geometric progression from the last spacing of the wall resolved region up to
+//! `y_length`. All lengths are scaled by the boundary layer thickness at the inflow, so the
+//! (friction) Reynolds number converts them to wall units.
+//!
+//! the y grid is not a port of the solver's routine and has not been compared with a `y.dat`
+//! written by the solver, so it is only an approximation: every figure derived from it is printed
+//! as approximate, and configs that cannot be stretched to the wall spacing are reported as
+//! warnings.
+
+use crate::prelude::*;
+
+/// height of the first cell at the wall in wall units (`dy+_w` in input.dat)
+pub(crate) const DY_PLUS_WALL: f64 = 0.7;
+
+/// how the solver builds the y grid (`jbgrid` in input.dat). 0 generates the stretched grid
+/// described in the module documentation
+pub(crate) const JBGRID: usize = 0;
+
+/// `sinh` overflows past this, so stronger stretching of the wall resolved region is not possible
+const MAX_STRETCHING: f64 = 700.;
+
+const BISECTION_ITERATIONS: usize = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum MeshError {
+    #[error("the mesh of channel flows is not previewed, only boundary layer flows are supported")]
+    ChannelFlow,
+    #[error("nymax-wr ({nymax_wr}) must be at least 2 and less than y-divisions ({y_divisions})")]
+    WallResolvedPoints { nymax_wr: usize, y_divisions: usize },
+    #[error("rly-wr ({rly_wr}) must be positive and less than y-length ({y_length})")]
+    WallResolvedLength { rly_wr: f64, y_length: f64 },
+    #[error("the wall resolved region is too fine for a first cell of {dy_plus} wall units: {nymax_wr} uniform points up to rly-wr = {rly_wr} are already {uniform_y_plus:.3} wall units apart. Decrease nymax-wr or increase rly-wr", dy_plus = DY_PLUS_WALL)]
+    WallResolvedTooFine {
+        nymax_wr: usize,
+        rly_wr: f64,
+        uniform_y_plus: f64,
+    },
+    #[error("the wall resolved region is too coarse for a first cell of {dy_plus} wall units. Increase nymax-wr or decrease rly-wr", dy_plus = DY_PLUS_WALL)]
+    WallResolvedTooCoarse,
+}
+
+/// the grid that the solver is expected to generate for a config
+#[derive(Debug, Clone)]
+pub(crate) struct ExpectedMesh {
+    pub(crate) x_data: Vec<f64>,
+    pub(crate) y_data: Vec<f64>,
+    pub(crate) z_data: Vec<f64>,
+    /// number of points in y that belong to the wall resolved region
+    wall_resolved_points: usize,
+}
+
+impl ExpectedMesh {
+    pub(crate) fn from_config(config: &Config) -> Result<Self, MeshError> {
+        if matches!(config.flow_type, cli::FlowType::ChannelFlow) {
+            return Err(MeshError::ChannelFlow);
+        }
+
+        let x_data = (0..config.x_divisions)
+            .map(|i| i as f64 * config.x_length / (config.x_divisions.max(2) - 1) as f64)
+            .collect();
+
+        // z is periodic, so the last point is one spacing short of the domain length
+        let z_data = (0..config.z_divisions)
+            .map(|k| k as f64 * config.z_length / config.z_divisions as f64)
+            .collect();
+
+        let y_data = y_grid(config)?;
+
+        Ok(Self {
+            x_data,
+            y_data,
+            z_data,
+            wall_resolved_points: config.nymax_wr,
+        })
+    }
+
+    /// resolution of the mesh in wall units, with the friction Reynolds number of the inflow
+    pub(crate) fn quality(&self, reynolds_number: f64) -> MeshQuality {
+        let spacing = |data: &[f64]| data.get(1).copied().unwrap_or(0.) - data[0];
+
+        let dy = self
+            .y_data
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect::<Vec<_>>();
+
+        let max_ratio = |spacings: &[f64]| {
+            spacings
+                .windows(2)
+                .map(|pair| pair[1] / pair[0])
+                .fold(1., f64::max)
+        };
+
+        // the outer region grows from the last spacing of the wall resolved region
+        let (wall_resolved, outer) = dy.split_at(self.wall_resolved_points - 1);
+
+        MeshQuality {
+            first_cell_y_plus: dy[0] * reynolds_number,
+            dx_plus: spacing(&self.x_data) * reynolds_number,
+            dz_plus: spacing(&self.z_data) * reynolds_number,
+            wall_resolved_stretching: max_ratio(wall_resolved),
+            outer_stretching: max_ratio(&dy[wall_resolved.len() - 1..]),
+            outer_spacing: outer.last().copied().unwrap_or(0.),
+            cells_in_boundary_layer: self.y_data.iter().filter(|y| **y <= 1.).count(),
+        }
+    }
+}
+
+/// a summary of how well a mesh resolves the boundary layer
+#[derive(Debug, Clone)]
+pub(crate) struct MeshQuality {
+    /// height of the first cell at the wall in wall units
+    pub(crate) first_cell_y_plus: f64,
+    pub(crate) dx_plus: f64,
+    pub(crate) dz_plus: f64,
+    /// largest ratio of neighbouring y spacings in the wall resolved region
+    pub(crate) wall_resolved_stretching: f64,
+    /// largest ratio of neighbouring y spacings from the edge of the wall resolved region outwards
+    pub(crate) outer_stretching: f64,
+    /// y spacing at the top of the domain
+    pub(crate) outer_spacing: f64,
+    /// number of points in y inside the boundary layer at the inflow
+    pub(crate) cells_in_boundary_layer: usize,
+}
+
+impl MeshQuality {
+    pub(crate) fn print(&self) {
+        println!("expected mesh (the y grid is an approximation of the solver's):");
+        println!("    dx+: {:.2} dz+: {:.2}", self.dx_plus, self.dz_plus);
+        println!("    first cell y+: ~{:.3}", self.first_cell_y_plus);
+        println!(
+            "    max stretching ratio in y: ~{:.4} (wall resolved) ~{:.4} (outer)",
+            self.wall_resolved_stretching, self.outer_stretching
+        );
+        println!(
+            "    y spacing at the top of the domain: ~{:.4}",
+            self.outer_spacing
+        );
+        println!(
+            "    points inside the inflow boundary layer: ~{}",
+            self.cells_in_boundary_layer
+        );
+    }
+}
+
+/// the stretched y grid for boundary layer flows, see the module documentation
+fn y_grid(config: &Config) -> Result<Vec<f64>, MeshError> {
+    let nymax_wr = config.nymax_wr;
+    let rly_wr = config.rly_wr;
+
+    if nymax_wr < 2 || nymax_wr >= config.y_divisions {
+        return Err(MeshError::WallResolvedPoints {
+            nymax_wr,
+            y_divisions: config.y_divisions,
+        });
+    }
+
+    if rly_wr <= 0. || rly_wr >= config.y_length {
+        return Err(MeshError::WallResolvedLength {
+            rly_wr,
+            y_length: config.y_length,
+        });
+    }
+
+    let wall_spacing = DY_PLUS_WALL / config.reynolds_number;
+    let cells = (nymax_wr - 1) as f64;
+
+    // first spacing of the wall resolved region. It is uniform for alpha = 0 and shrinks as
+    // the stretching increases
+    let first_spacing = |alpha: f64| {
+        if alpha == 0. {
+            rly_wr / cells
+        } else {
+            rly_wr * (alpha / cells).sinh() / alpha.sinh()
+        }
+    };
+
+    if first_spacing(0.) < wall_spacing {
+        return Err(MeshError::WallResolvedTooFine {
+            nymax_wr,
+            rly_wr,
+            uniform_y_plus: first_spacing(0.) * config.reynolds_number,
+        });
+    }
+
+    if first_spacing(MAX_STRETCHING) > wall_spacing {
+        return Err(MeshError::WallResolvedTooCoarse);
+    }
+
+    let alpha = bisect(0., MAX_STRETCHING, |alpha| {
+        first_spacing(alpha) > wall_spacing
+    });
+
+    let mut y = (0..nymax_wr)
+        .map(|j| {
+            if alpha == 0. {
+                rly_wr * j as f64 / cells
+            } else {
+                rly_wr * (alpha * j as f64 / cells).sinh() / alpha.sinh()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // geometric progression from the last wall resolved spacing up to the top of the domain
+    let last_spacing = y[nymax_wr - 1] - y[nymax_wr - 2];
+    let outer_points = config.y_divisions - nymax_wr;
+    let outer_length = config.y_length - rly_wr;
+
+    let covered = |ratio: f64| {
+        (1..=outer_points)
+            .map(|k| last_spacing * ratio.powi(k as i32))
+            .sum::<f64>()
+    };
+
+    let mut upper = 2.;
+    while covered(upper) < outer_length {
+        upper *= 2.;
+    }
+
+    let ratio = bisect(0., upper, |ratio| covered(ratio) < outer_length);
+
+    let mut spacing = last_spacing;
+    for _ in 0..outer_points {
+        spacing *= ratio;
+        let next = y.last().unwrap() + spacing;
+        y.push(next);
+    }
+
+    // remove the round off from the bisection
+    *y.last_mut().unwrap() = config.y_length;
+
+    Ok(y)
+}
+
+/// find where `below` changes from true to false in `lower..upper`
+fn bisect(mut lower: f64, mut upper: f64, below: impl Fn(f64) -> bool) -> f64 {
+    for _ in 0..BISECTION_ITERATIONS {
+        let middle = 0.5 * (lower + upper);
+        if below(middle) {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+
+    0.5 * (lower + upper)
+}
+
+#[test]
+fn default_mesh_matches_wall_spacing() {
+    let config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    let mesh = ExpectedMesh::from_config(&config).unwrap();
+
+    assert_eq!(mesh.y_data.len(), config.y_divisions);
+    assert_eq!(mesh.y_data[0], 0.);
+    assert!((mesh.y_data[config.nymax_wr - 1] - config.rly_wr).abs() < 1e-12);
+    assert_eq!(*mesh.y_data.last().unwrap(), config.y_length);
+    assert!(mesh.y_data.windows(2).all(|pair| pair[1] > pair[0]));
+
+    let quality = mesh.quality(config.reynolds_number);
+    assert!((quality.first_cell_y_plus - DY_PLUS_WALL).abs() < 1e-9);
+    assert!(quality.wall_resolved_stretching > 1.);
+    assert!(quality.cells_in_boundary_layer > 100);
+}
+
+#[test]
+fn fine_wall_resolved_region_is_an_error() {
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    // 0.5 / 200 is 0.625 wall units at the default Reynolds number
+    config.rly_wr = 0.5;
+
+    assert!(matches!(
+        ExpectedMesh::from_config(&config),
+        Err(MeshError::WallResolvedTooFine { .. })
+    ));
+}
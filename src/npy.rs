@@ -0,0 +1,137 @@
+//! writers for NumPy `.npy` and `.npz` files so that arrays can be loaded with `np.load`
+//!
+//! arrays are written as little endian `f64` in C order (version 1.0 of the format)
+
+use crate::prelude::*;
+use ndarray::{ArrayBase, Data, Dimension};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// the header (magic string, version, header length and header dictionary) of a `.npy` file
+fn header(shape: &[usize]) -> Vec<u8> {
+    // a 1 dimensional shape needs a trailing comma to be a python tuple
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|len| len.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    let mut dict = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}");
+
+    // the data must start on a 64 byte boundary: magic (6) + version (2) + length (2) + dict,
+    // where the dict is padded with spaces and terminated by a newline
+    let unpadded = MAGIC.len() + 2 + 2 + dict.len() + 1;
+    let padding = (64 - unpadded % 64) % 64;
+    dict.push_str(&" ".repeat(padding));
+    dict.push('\n');
+
+    let mut header = Vec::with_capacity(unpadded + padding);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+
+    header
+}
+
+/// write an array in the `.npy` format
+pub(crate) fn write_npy<W, S, D>(mut writer: W, array: &ArrayBase<S, D>) -> io::Result<()>
+where
+    W: Write,
+    S: Data<Elem = f64>,
+    D: Dimension,
+{
+    writer.write_all(&header(array.shape()))?;
+
+    // iterating an array always visits elements in logical (C) order, regardless of its layout
+    for value in array.iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
+
+/// write an array to a `.npy` file
+pub(crate) fn write_npy_file<S, D>(path: &Path, array: &ArrayBase<S, D>) -> Result<(), Error>
+where
+    S: Data<Elem = f64>,
+    D: Dimension,
+{
+    let writer =
+        io::BufWriter::new(fs::File::create(path).map_err(|e| FileError::new(path.to_owned(), e))?);
+    write_npy(writer, array).map_err(|e| FileError::new(path.to_owned(), e))?;
+
+    Ok(())
+}
+
+/// an (uncompressed) `.npz` archive of named arrays
+pub(crate) struct NpzWriter {
+    path: PathBuf,
+    zip: zip::ZipWriter<io::BufWriter<fs::File>>,
+}
+
+impl NpzWriter {
+    pub(crate) fn create(path: &Path) -> Result<Self, Error> {
+        let file = fs::File::create(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            zip: zip::ZipWriter::new(io::BufWriter::new(file)),
+        })
+    }
+
+    /// add an array that will be available as `npz[name]` in python
+    pub(crate) fn add_array<S, D>(
+        &mut self,
+        name: &str,
+        array: &ArrayBase<S, D>,
+    ) -> Result<(), Error>
+    where
+        S: Data<Elem = f64>,
+        D: Dimension,
+    {
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            // arrays of more than 4 GB need zip64 extensions
+            .large_file(array.len() * 8 > u32::MAX as usize);
+
+        self.zip.start_file(format!("{name}.npy"), options)?;
+        write_npy(&mut self.zip, array).map_err(|e| FileError::new(self.path.clone(), e))?;
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<(), Error> {
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn npy_header_is_aligned() {
+    let array = ndarray::Array2::from_shape_vec((2, 3), vec![0., 1., 2., 3., 4., 5.]).unwrap();
+
+    let mut buffer = Vec::new();
+    write_npy(&mut buffer, &array.t()).unwrap();
+
+    let header_len = u16::from_le_bytes([buffer[8], buffer[9]]) as usize;
+    let data_start = 10 + header_len;
+    assert_eq!(data_start % 64, 0);
+
+    let dict = std::str::from_utf8(&buffer[10..data_start]).unwrap();
+    assert!(dict.contains("'shape': (3, 2)"));
+    assert!(dict.ends_with('\n'));
+
+    // the transposed array is written in its logical order
+    let values = buffer[data_start..]
+        .chunks(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![0., 3., 1., 4., 2., 5.]);
+}
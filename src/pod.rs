@@ -0,0 +1,272 @@
+//! proper orthogonal decomposition of span average snapshots
+//!
+//! modes are computed with the method of snapshots: the eigenvectors of the (small)
+//! `num_snapshots x num_snapshots` correlation matrix are projected back onto the snapshots to
+//! recover the spatial modes, which is far cheaper than decomposing the `4 * nx * ny` square
+//! spatial correlation matrix.
+
+use crate::prelude::*;
+use crate::snapshots;
+use crate::snapshots::SpanSnapshots;
+use crate::snapshots::SNAPSHOT_FIELDS;
+
+use ndarray::Array1;
+use ndarray::Axis;
+
+/// energy and temporal information of the decomposition, written to pod.mat
+#[derive(mat5::MatFile)]
+struct PodResults {
+    /// eigenvalues of the snapshot correlation matrix (the energy of each mode), largest first
+    energy: Vec<f64>,
+    /// fraction of the total energy captured by the first n modes
+    cumulative_energy: Vec<f64>,
+    /// `(num_snapshots, num_modes)` temporal coefficients of each mode
+    temporal_coefficients: Array2,
+    /// solver step of each snapshot
+    steps: Vec<f64>,
+}
+
+pub(crate) fn pod(args: cli::Pod) -> anyhow::Result<()> {
+    let config_path = args.solver_results.join("input.json");
+    let config = Config::load(&config_path, &args.overrides)
+        .with_context(|| format!("failed to read config at path {}", config_path.display()))?;
+
+    let mesh = run::MeshInfo::from_base_path(&args.solver_results, &config)?;
+
+    let mut snapshots = SpanSnapshots::from_solver_results(&args.solver_results, &config)?;
+    let num_snapshots = snapshots.num_snapshots();
+
+    if num_snapshots < 2 {
+        anyhow::bail!("at least two span averages are required for POD, found {num_snapshots}");
+    }
+
+    let mean = if args.keep_mean {
+        None
+    } else {
+        let mean = snapshots
+            .data
+            .mean_axis(Axis(0))
+            .expect("there is more than one snapshot");
+        snapshots.data -= &mean;
+        Some(mean)
+    };
+
+    let weights = if args.unweighted {
+        Array1::ones(SNAPSHOT_FIELDS * config.x_divisions * config.y_divisions)
+    } else {
+        quadrature_weights(&mesh.x_data, &mesh.y_data)
+    };
+
+    println!("computing {num_snapshots} x {num_snapshots} snapshot correlation matrix");
+    let decomposition = method_of_snapshots(snapshots.matrix(), &weights, args.modes);
+
+    //
+    // write the results
+    //
+
+    if !args.output_directory.exists() {
+        fs::create_dir_all(&args.output_directory).with_context(|| {
+            format!(
+                "failed to create output directory {}",
+                args.output_directory.display()
+            )
+        })?;
+    }
+
+    let domain = snapshots::span_domain(&mesh, &config);
+
+    let field_shape = (SNAPSHOT_FIELDS, config.x_divisions, config.y_divisions);
+
+    if let Some(mean) = mean {
+        snapshots::write_snapshot_vtk(
+            &args.output_directory.join("pod_mean.vtr"),
+            domain.clone(),
+            mean,
+        )?;
+    }
+
+    for (idx, mode) in decomposition.modes.outer_iter().enumerate() {
+        let mode = mode
+            .to_owned()
+            .into_shape(field_shape)
+            .expect("mode has the same number of points as a snapshot");
+
+        snapshots::write_snapshot_vtk(
+            &args.output_directory.join(format!("pod_mode_{idx:03}.vtr")),
+            domain.clone(),
+            mode,
+        )?;
+    }
+
+    let total_energy: f64 = decomposition.energy.iter().sum();
+    let cumulative_energy = decomposition
+        .energy
+        .iter()
+        .scan(0., |sum, energy| {
+            *sum += energy;
+            Some(*sum / total_energy)
+        })
+        .collect();
+
+    let results = PodResults {
+        energy: decomposition.energy,
+        cumulative_energy,
+        temporal_coefficients: decomposition.temporal_coefficients,
+        steps: snapshots.steps.iter().map(|step| *step as f64).collect(),
+    };
+
+    let mat_path = args.output_directory.join("pod.mat");
+    let writer = io::BufWriter::new(
+        fs::File::create(&mat_path)
+            .with_context(|| format!("failed to create {}", mat_path.display()))?,
+    );
+    mat5::MatFile::write_contents(&results, writer)?;
+
+    Ok(())
+}
+
+/// quadrature weights (the area of the cell around each point) for every entry of a flattened
+/// `(4, nx, ny)` snapshot
+pub(crate) fn quadrature_weights(x: &[f64], y: &[f64]) -> Array1<f64> {
+    let dx = cell_widths(x);
+    let dy = cell_widths(y);
+
+    let mut weights = Array3::zeros((SNAPSHOT_FIELDS, x.len(), y.len()));
+
+    for mut field in weights.outer_iter_mut() {
+        for ((i, j), weight) in field.indexed_iter_mut() {
+            *weight = dx[i] * dy[j];
+        }
+    }
+
+    let len = weights.len();
+    weights
+        .into_shape(len)
+        .expect("weights are allocated in standard layout")
+}
+
+/// trapezoidal widths of the cells around each point of a (possibly non uniform) grid
+fn cell_widths(points: &[f64]) -> Vec<f64> {
+    let n = points.len();
+
+    if n < 2 {
+        return vec![1.; n];
+    }
+
+    (0..n)
+        .map(|i| {
+            let left = if i == 0 { points[0] } else { points[i - 1] };
+            let right = if i == n - 1 {
+                points[n - 1]
+            } else {
+                points[i + 1]
+            };
+            (right - left) / 2.
+        })
+        .collect()
+}
+
+struct Decomposition {
+    /// energy of every mode, largest first
+    energy: Vec<f64>,
+    /// `(num_modes, num_points)` spatial modes, orthonormal in the weighted inner product
+    modes: Array2,
+    /// `(num_snapshots, num_modes)` projection of each snapshot onto each mode
+    temporal_coefficients: Array2,
+}
+
+/// decompose a `(num_snapshots, num_points)` snapshot matrix, keeping at most `num_modes`
+/// spatial modes. The energy of every mode is always returned.
+fn method_of_snapshots(
+    snapshots: ndarray::ArrayView2<f64>,
+    weights: &Array1<f64>,
+    num_modes: usize,
+) -> Decomposition {
+    let num_snapshots = snapshots.nrows();
+
+    let weighted = &snapshots * &weights.view().insert_axis(Axis(0));
+    let correlation = weighted.dot(&snapshots.t()) / num_snapshots as f64;
+
+    let correlation = nalgebra::DMatrix::from_fn(num_snapshots, num_snapshots, |i, j| {
+        // symmetrize away any floating point error before decomposing
+        0.5 * (correlation[[i, j]] + correlation[[j, i]])
+    });
+    let eigen = nalgebra::SymmetricEigen::new(correlation);
+
+    let mut order = (0..num_snapshots).collect::<Vec<_>>();
+    order.sort_unstable_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+
+    // the correlation matrix is positive semi definite, negative values are round off
+    let energy = order
+        .iter()
+        .map(|&idx| eigen.eigenvalues[idx].max(0.))
+        .collect::<Vec<_>>();
+
+    // modes with no energy (relative to round off in the largest mode) cannot be normalized
+    let threshold = energy.first().copied().unwrap_or(0.) * 1e-12;
+    let num_modes = energy
+        .iter()
+        .take(num_modes)
+        .take_while(|energy| **energy > threshold)
+        .count();
+
+    let mut temporal_coefficients = Array2::zeros((num_snapshots, num_modes));
+    let mut eigenvectors = Array2::zeros((num_modes, num_snapshots));
+
+    for (mode, &idx) in order.iter().take(num_modes).enumerate() {
+        let scale = (num_snapshots as f64 * energy[mode]).sqrt();
+
+        for snapshot in 0..num_snapshots {
+            let value = eigen.eigenvectors[(snapshot, idx)];
+            eigenvectors[[mode, snapshot]] = value / scale;
+            temporal_coefficients[[snapshot, mode]] = value * scale;
+        }
+    }
+
+    let modes = eigenvectors.dot(&snapshots);
+
+    Decomposition {
+        energy,
+        modes,
+        temporal_coefficients,
+    }
+}
+
+#[test]
+/// snapshots built from two orthogonal structures should decompose into exactly two modes that
+/// reconstruct the data
+fn pod_recovers_rank_two_data() {
+    let num_points = 50;
+    let num_snapshots = 20;
+
+    let x = (0..num_points)
+        .map(|i| i as f64 / num_points as f64)
+        .collect::<Vec<_>>();
+
+    let snapshots = Array2::from_shape_fn((num_snapshots, num_points), |(t, i)| {
+        let t = t as f64 * 0.3;
+        let phase = 2. * std::f64::consts::PI * x[i];
+        3. * t.sin() * phase.sin() + 0.5 * t.cos() * (2. * phase).cos()
+    });
+
+    let weights = Array1::ones(num_points);
+    let decomposition = method_of_snapshots(snapshots.view(), &weights, 5);
+
+    assert_eq!(decomposition.modes.nrows(), 2);
+    assert!(decomposition.energy[0] > decomposition.energy[1]);
+
+    // modes should be orthonormal
+    let gram = decomposition.modes.dot(&decomposition.modes.t());
+    for ((i, j), value) in gram.indexed_iter() {
+        let expected = if i == j { 1. } else { 0. };
+        assert!((value - expected).abs() < 1e-8);
+    }
+
+    // and the modes with their coefficients reconstruct the snapshots
+    let reconstruction = decomposition
+        .temporal_coefficients
+        .dot(&decomposition.modes);
+    for (a, b) in reconstruction.iter().zip(snapshots.iter()) {
+        assert!((a - b).abs() < 1e-8);
+    }
+}
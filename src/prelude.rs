@@ -8,7 +8,7 @@ pub(crate) use crate::FileError;
 
 pub(crate) use std::fs;
 pub(crate) use std::io;
-pub(crate) use std::io::{Read, Write};
+pub(crate) use std::io::Write;
 pub(crate) use std::path::Path;
 pub(crate) use std::path::PathBuf;
 
@@ -1,26 +1,36 @@
-//! handles cli commands for exporting probe data to .mat files
+//! handles cli commands for exporting probe data to .mat (or hdf5 / netcdf) files
 
 use crate::prelude::*;
+use crate::probe_binary::ProbeBinaryError;
+use crate::probe_output::ProbeOutput;
+use std::collections::BTreeMap;
 
 /// for a given configuration file and probe directory, parse the probe binary information and
-/// transform the data to .mat files
+/// transform the data to files in the chosen format
 pub(crate) fn probe(args: cli::ParseProbe) -> Result<(), Error> {
-    let config = Config::from_path(&args.config)?;
+    let config = Config::load(&args.config, &args.overrides)?;
 
     // group all of the probes data together
-    let paths = fs::read_dir(&args.probe_directory)
-        .map_err(|e| FileError::new(args.probe_directory.clone(), e))?
-        .into_iter()
-        .filter_map(|entry_res| entry_res.ok())
-        .map(|entry: fs::DirEntry| entry.path())
-        .filter(|entry_path| {
-            entry_path
-                .file_name()
-                .map(|name| name.to_str().unwrap().contains("span_probe"))
-                .unwrap_or(false)
-        });
+    let paths = probe_paths(&args.probe_directory)?;
+    let grouping = group_probes_by_number(paths.into_iter(), args.keep_going)?;
 
-    let grouping = group_probes_by_number(paths);
+    // the mesh is only used to annotate the probes with their physical coordinates, so
+    // a missing mesh is not fatal
+    let mesh_directory = args
+        .mesh
+        .clone()
+        .or_else(|| args.config.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let mesh = match run::MeshInfo::from_base_path(&mesh_directory, &config) {
+        Ok(mesh) => Some(mesh),
+        Err(e) => {
+            println!(
+                "could not read mesh information from {} ({e}), probe coordinates will not be written",
+                mesh_directory.display()
+            );
+            None
+        }
+    };
 
     // create the output directory if it does not exist
     if !args.output_directory.exists() {
@@ -28,139 +38,188 @@ pub(crate) fn probe(args: cli::ParseProbe) -> Result<(), Error> {
             .map_err(|e| FileError::new(args.output_directory.clone(), e))?;
     }
 
-    // helper function to create a writer for the given .mat file destination path and parse the data to
-    // that file
-    fn parse_probe_helper(
-        probe_info: Vec<ProbeInfo>,
-        config: &Config,
-        path: &Path,
-    ) -> Result<(), Error> {
-        let writer = io::BufWriter::new(
-            std::fs::File::create(path).map_err(|e| FileError::new(path.to_owned(), e))?,
-        );
+    for (probe_number, probe_info) in grouping.groups() {
+        let path = args
+            .output_directory
+            .join(format!("probe_{probe_number}.{}", args.format.extension()));
+
         let paths = probe_info
-            .into_iter()
-            .map(|info| info.path)
+            .iter()
+            .map(|info| info.path.clone())
             .collect::<Vec<_>>();
 
-        crate::probe_binary::parse_file_group(paths.as_slice(), config.z_divisions, writer)?;
-        Ok(())
-    }
+        let (data, parsed) = crate::probe_binary::read_file_group(
+            paths.as_slice(),
+            config.z_divisions,
+            config.mpi_z_split,
+            args.keep_going,
+        )?;
+
+        if parsed.len() != probe_info.len() {
+            println!(
+                "probe {probe_number}: skipped {} of {} files",
+                probe_info.len() - parsed.len(),
+                probe_info.len()
+            );
+        }
+
+        if parsed.is_empty() {
+            continue;
+        }
 
-    parse_probe_helper(
-        grouping.one,
-        &config,
-        &args.output_directory.join("probe_1.mat"),
-    )?;
-    parse_probe_helper(
-        grouping.two,
-        &config,
-        &args.output_directory.join("probe_2.mat"),
-    )?;
-    parse_probe_helper(
-        grouping.three,
-        &config,
-        &args.output_directory.join("probe_3.mat"),
-    )?;
+        let output = ProbeOutput {
+            data,
+            steps: parsed
+                .iter()
+                .map(|idx| probe_info[*idx].step_number)
+                .collect(),
+            location: ProbeLocation::new(probe_number, &config, mesh.as_ref()),
+            z_coordinates: mesh.as_ref().map(|mesh| mesh.z_data.as_slice()),
+            config: &config,
+        };
+
+        output.write(&path, args.format)?;
+    }
 
     Ok(())
 }
 
+/// where a probe is located in the domain. Fields are NaN if they could not be determined
+#[derive(Debug, PartialEq)]
+pub(crate) struct ProbeLocation {
+    /// (1 indexed) grid point index of the probe in x
+    pub(crate) x_index: f64,
+    /// (1 indexed) grid point index of the probe in z
+    pub(crate) z_index: f64,
+    /// physical x coordinate of the probe
+    pub(crate) x: f64,
+    /// physical z coordinate of the probe
+    pub(crate) z: f64,
+}
+
+impl ProbeLocation {
+    /// probe numbers are 1 indexed by the solver and correspond to the (x, z) pairs of
+    /// `probe_locations_x` and `probe_locations_z` in the config, which are also 1 indexed
+    fn new(probe_number: usize, config: &Config, mesh: Option<&run::MeshInfo>) -> Self {
+        let idx = probe_number.checked_sub(1);
+        let x_index = idx.and_then(|idx| config.probe_locations_x.get(idx).copied());
+        let z_index = idx.and_then(|idx| config.probe_locations_z.get(idx).copied());
+
+        let coordinate = |index: Option<usize>, data: Option<&Vec<f64>>| {
+            index
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| data.and_then(|data| data.get(index).copied()))
+                .unwrap_or(f64::NAN)
+        };
+
+        Self {
+            x_index: x_index.map(|x| x as f64).unwrap_or(f64::NAN),
+            z_index: z_index.map(|z| z as f64).unwrap_or(f64::NAN),
+            x: coordinate(x_index, mesh.map(|mesh| &mesh.x_data)),
+            z: coordinate(z_index, mesh.map(|mesh| &mesh.z_data)),
+        }
+    }
+}
+
+/// find all of the probe binary files written by the solver in a given directory
+pub(crate) fn probe_paths(probe_directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let paths = fs::read_dir(probe_directory)
+        .map_err(|e| FileError::new(probe_directory.to_owned(), e))?
+        .into_iter()
+        .filter_map(|entry_res| entry_res.ok())
+        .map(|entry: fs::DirEntry| entry.path())
+        .filter(|entry_path| {
+            entry_path
+                .file_name()
+                .map(|name| name.to_string_lossy().contains("span_probe"))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    Ok(paths)
+}
+
 #[derive(PartialEq, Eq, Debug)]
 /// metadata parsed from probe filesystem path on where it is
 /// and the timestep at which the data was collected
-struct ProbeInfo {
-    path: PathBuf,
-    step_number: usize,
-    probe_number: usize,
+pub(crate) struct ProbeInfo {
+    pub(crate) path: PathBuf,
+    pub(crate) step_number: usize,
+    pub(crate) probe_number: usize,
 }
 
 /// all probe paths for a given run, grouped by the fortran probe_number and ordered
 /// by the step at which they were written
-struct ProbeGrouping {
-    one: Vec<ProbeInfo>,
-    two: Vec<ProbeInfo>,
-    three: Vec<ProbeInfo>,
+pub(crate) struct ProbeGrouping {
+    probes: BTreeMap<usize, Vec<ProbeInfo>>,
+}
+
+impl ProbeGrouping {
+    /// iterate over each of the probe numbers (in increasing order) and the files associated
+    /// with them
+    pub(crate) fn groups(&self) -> impl Iterator<Item = (usize, &[ProbeInfo])> {
+        self.probes
+            .iter()
+            .map(|(probe_number, probes)| (*probe_number, probes.as_slice()))
+    }
 }
 
-fn group_probes_by_number(probe_paths: impl Iterator<Item = PathBuf>) -> ProbeGrouping {
-    let mut one = Vec::new();
-    let mut two = Vec::new();
-    let mut three = Vec::new();
-
-    probe_paths
-        //.into_iter()
-        .map(probe_metadata)
-        .for_each(|probe_meta: ProbeInfo| {
-            // match the probe number to the container that it belongs to
-            //
-            // this could be made more robust with hashmaps but I elect for it to
-            // be more explicit for clairty
-            if probe_meta.probe_number == 1 {
-                one.push(probe_meta);
-            } else if probe_meta.probe_number == 2 {
-                two.push(probe_meta);
-            } else if probe_meta.probe_number == 3 {
-                three.push(probe_meta);
-            } else {
-                panic!(
-                    "unknown probe number parsed ({}) for path {}",
-                    probe_meta.probe_number,
-                    probe_meta.path.display()
-                );
+/// group probe files by their probe number. Files whose names cannot be parsed are an error,
+/// unless `keep_going` is set, in which case they are reported and skipped
+pub(crate) fn group_probes_by_number(
+    probe_paths: impl Iterator<Item = PathBuf>,
+    keep_going: bool,
+) -> Result<ProbeGrouping, ProbeBinaryError> {
+    let mut probes: BTreeMap<usize, Vec<ProbeInfo>> = BTreeMap::new();
+
+    for probe in probe_paths {
+        let probe_meta = match probe_metadata(probe) {
+            Ok(probe_meta) => probe_meta,
+            Err(e) if keep_going => {
+                println!("skipping probe file: {e}");
+                continue;
             }
-        });
+            Err(e) => return Err(e),
+        };
+
+        probes
+            .entry(probe_meta.probe_number)
+            .or_default()
+            .push(probe_meta);
+    }
 
     // make sure they are ordered by the step number
-    one.sort_unstable_by_key(|x| x.step_number);
-    two.sort_unstable_by_key(|x| x.step_number);
-    three.sort_unstable_by_key(|x| x.step_number);
+    for group in probes.values_mut() {
+        group.sort_unstable_by_key(|x| x.step_number);
+    }
 
-    ProbeGrouping { one, two, three }
+    Ok(ProbeGrouping { probes })
 }
 
 /// parse the probe number and step at which a binary file of probe data was written at
-// TODO: better error handling in this function
-fn probe_metadata(probe: PathBuf) -> ProbeInfo {
-    let filename = probe
-        .file_name()
-        .expect(&format!(
-            "probe file {} was missing a file name",
-            probe.display()
-        ))
-        .to_string_lossy();
+fn probe_metadata(probe: PathBuf) -> Result<ProbeInfo, ProbeBinaryError> {
+    let filename = match probe.file_name() {
+        Some(filename) => filename.to_string_lossy(),
+        None => return Err(ProbeBinaryError::FileName(probe)),
+    };
 
     // based on the fortran code the file name follows this format
-    // span_probe_[1 char]_[5 char step number].binary
-    let header = "span_probe";
-    let sep = "_";
-
-    let probe_number_length = 1;
-    let cycle_number_length = 5;
-
-    let probe_num_start = header.len() + sep.len();
-    let cycle_num_start = probe_num_start + sep.len() + probe_number_length;
-
-    let probe_number_str = filename
-        .get(probe_num_start..probe_num_start + probe_number_length)
-        .unwrap();
-    let probe_step_str = filename
-        .get(cycle_num_start..cycle_num_start + cycle_number_length)
-        .unwrap();
-
-    let probe_number = probe_number_str.parse().expect(&format!(
-        "failed to parse probe number for file name {} - probe number string to parse was {}",
-        filename, probe_number_str
-    ));
-    let step_number = probe_step_str.parse().expect(&format!(
-        "failed to parse probe step for file name {} - probe number string to parse was {}",
-        filename, probe_step_str
-    ));
-
-    ProbeInfo {
-        path: probe,
-        step_number,
-        probe_number,
+    // span_probe_[probe number]_[5 char step number].binary
+    //
+    // the probe number is not padded, so it may be any number of characters
+    let stem = filename.split('.').next().unwrap_or_default();
+    let mut parts = stem.split('_').skip_while(|part| *part != "probe").skip(1);
+
+    let probe_number = parts.next().and_then(|number| number.parse().ok());
+    let step_number = parts.next().and_then(|step| step.parse().ok());
+
+    match (probe_number, step_number) {
+        (Some(probe_number), Some(step_number)) => Ok(ProbeInfo {
+            path: probe,
+            step_number,
+            probe_number,
+        }),
+        _ => Err(ProbeBinaryError::FileName(probe)),
     }
 }
 
@@ -172,6 +231,29 @@ fn check_probe_parse() {
         step_number: 99999,
         probe_number: 1,
     };
-    let parsed_info = probe_metadata(path);
+    let parsed_info = probe_metadata(path).unwrap();
     assert_eq!(expected, parsed_info);
+
+    let invalid = PathBuf::from("./some/probe/path/span_probe_x_99999.binary");
+    assert!(probe_metadata(invalid).is_err());
+}
+
+#[test]
+fn check_multi_digit_probe_parse() {
+    let paths = [
+        "./csv_data/span_probe_12_00200.binary",
+        "./csv_data/span_probe_2_00100.binary",
+        "./csv_data/span_probe_12_00100.binary",
+    ];
+    let grouping = group_probes_by_number(paths.iter().map(PathBuf::from), false).unwrap();
+
+    let groups = grouping
+        .groups()
+        .map(|(number, probes)| {
+            let steps = probes.iter().map(|p| p.step_number).collect::<Vec<_>>();
+            (number, steps)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(groups, vec![(2, vec![100]), (12, vec![100, 200])]);
 }
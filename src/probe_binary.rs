@@ -12,11 +12,21 @@ pub(crate) enum ProbeBinaryError {
     File(FileError),
     #[error("Could not write the file using mat5: {0}")]
     Mat5(mat5::Error),
+    #[error("probe file {} could not be read: {error}", path.display())]
+    #[from(ignore)]
+    Truncated {
+        path: PathBuf,
+        error: utils::TruncatedBytes,
+    },
+    #[error("could not parse the probe number and step from the file name of {}. Expected a name like span_probe_[probe number]_[step number].binary", .0.display())]
+    #[from(ignore)]
+    FileName(PathBuf),
 }
 
 #[derive(Display, Debug, Constructor)]
 #[display(
-    fmt = "Data was unexpected length for nz = {}. Length was {} but expected a length of {} ({} difference)",
+    fmt = "Data in {} was unexpected length for nz = {}. Length was {} bytes but expected a length of {} bytes ({} bytes difference)",
+    "path.display()",
     nz,
     length,
     expected_length,
@@ -24,12 +34,21 @@ pub(crate) enum ProbeBinaryError {
 )]
 /// Represents an error caused by data being an unexpected length
 pub(crate) struct Length {
+    path: PathBuf,
     nz: usize,
     length: usize,
     expected_length: usize,
     difference: usize,
 }
 
+impl Length {
+    /// the length error for a buffer of `actual` floats when `expected` floats were required
+    fn from_floats(path: &Path, nz: usize, actual: usize, expected: usize) -> Self {
+        let diff = ((actual as isize) - (expected as isize)).unsigned_abs();
+        Self::new(path.to_owned(), nz, 8 * actual, 8 * expected, 8 * diff)
+    }
+}
+
 /// Array data as it is stored in a single file written by fortran
 struct ProbeFile {
     viscous: Array2,
@@ -48,112 +67,138 @@ struct ProbeFile {
 /// | 2nd       | Data type. This dimension should have 4 indicies for : rho, u, v, w |
 /// | 3rd       | Probe data at the given timestep and data type |
 #[derive(mat5::MatFile)]
-struct ProbeFileAllTimesteps {
-    viscous: Array3,
-    log_law: Array3,
-    freestream: Array3,
+pub(crate) struct ProbeFileAllTimesteps {
+    pub(crate) viscous: Array3,
+    pub(crate) log_law: Array3,
+    pub(crate) freestream: Array3,
 }
 
-/// multithreaded parser for probe data.
+/// multithreaded parser for probe data, returning every timestep in memory along with the
+/// indices of the files that were parsed
 ///
 /// The files send to this function must be sorted to the
 /// chronological order in which they were created as no attempt to
 /// sort them is made in this function.
-pub(crate) fn parse_file_group<W: Write>(
+///
+/// If `keep_going` is set, files that cannot be parsed are reported and left out of the output
+/// instead of returning an error.
+pub(crate) fn read_file_group(
     files: &[PathBuf],
     nz: usize,
-    writer: W,
-) -> Result<(), ProbeBinaryError> {
-    let parse_results: Result<Vec<(usize, ProbeFile)>, _> = files
+    mpi_z_split: usize,
+    keep_going: bool,
+) -> Result<(ProbeFileAllTimesteps, Vec<usize>), ProbeBinaryError> {
+    let parse_results: Vec<(usize, Result<ProbeFile, ProbeBinaryError>)> = files
         .into_par_iter()
         .enumerate()
         .map(|(idx, path)| {
             // load and parse the binary file in parallel
-            parse_binary_file(path.as_ref(), nz).map(|x| (idx, x))
+            (idx, parse_binary_file(path.as_ref(), nz, mpi_z_split))
         })
         .collect();
 
-    let shape = (files.len(), 4, nz);
+    let mut parsed = Vec::with_capacity(parse_results.len());
+
+    for (idx, result) in parse_results {
+        match result {
+            Ok(slice) => parsed.push((idx, slice)),
+            Err(e) if keep_going => println!("skipping corrupt probe file: {e}"),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let shape = (parsed.len(), 4, nz);
 
     let mut viscous = Array3::zeros(shape);
     let mut log_law = Array3::zeros(shape);
     let mut freestream = Array3::zeros(shape);
+    let mut indices = Vec::with_capacity(parsed.len());
 
     // copy all of the slices into big matricies
-    for (idx, slice) in parse_results? {
+    for (out_idx, (idx, slice)) in parsed.into_iter().enumerate() {
         viscous
-            .slice_mut(ndarray::s![idx, .., ..])
+            .slice_mut(ndarray::s![out_idx, .., ..])
             .assign(&slice.viscous);
         log_law
-            .slice_mut(ndarray::s![idx, .., ..])
+            .slice_mut(ndarray::s![out_idx, .., ..])
             .assign(&slice.log_law);
         freestream
-            .slice_mut(ndarray::s![idx, .., ..])
+            .slice_mut(ndarray::s![out_idx, .., ..])
             .assign(&slice.freestream);
+
+        indices.push(idx);
     }
 
-    // assemble the data and write it out to a file
     let probes = ProbeFileAllTimesteps {
         viscous,
         log_law,
         freestream,
     };
-    mat5::MatFile::write_contents(&probes, writer)?;
 
-    Ok(())
+    Ok((probes, indices))
 }
 
 /// load a single binary file to its constituent arrays
-fn parse_binary_file(path: &Path, nz: usize) -> Result<ProbeFile, ProbeBinaryError> {
-    let bytes = std::fs::read(path).map_err(|e| FileError::new(path.to_owned(), e))?;
-    let floats = utils::bytes_to_float(bytes.as_slice());
+///
+/// when the domain is split in z, the file holds one block for every process along z in order,
+/// each with the viscous, log law and freestream data of its own part of the span
+fn parse_binary_file(
+    path: &Path,
+    nz: usize,
+    mpi_z_split: usize,
+) -> Result<ProbeFile, ProbeBinaryError> {
+    let file = utils::FloatFile::open(path)?;
+    let floats = file.floats().map_err(|error| ProbeBinaryError::Truncated {
+        path: path.to_owned(),
+        error,
+    })?;
+
+    let z_split = mpi_z_split.max(1);
+    let nz_proc = nz / z_split;
+
+    // 4 pieces of information are written per data point, there are nz_proc data points for
+    // each of the 3 heights in a block
+    let slice_len = nz_proc * 4;
+    let block_len = slice_len * 3;
+
+    if block_len * z_split != floats.len() {
+        return Err(Length::from_floats(path, nz, floats.len(), block_len * z_split).into());
+    }
 
-    // 4 pieces of information are written per data point, there are nz data points
-    let slice_len = nz * 4;
+    let mut heights = vec![Array2::zeros((4, nz)); 3];
 
-    if slice_len * 3 != floats.len() {
-        let diff = ((floats.len() as isize) - (slice_len * 3) as isize).abs() as usize;
-        return Err(Length::new(nz, floats.len(), slice_len * 3, diff).into());
-    }
+    for (z_proc, block) in floats.chunks_exact(block_len).enumerate() {
+        let span = ndarray::s![.., z_proc * nz_proc..(z_proc + 1) * nz_proc];
 
-    let viscous_floats = floats.get(0..slice_len).unwrap();
-    let log_law_floats = floats.get(slice_len..2 * slice_len).unwrap();
-    let freestream_floats = floats.get(2 * slice_len..3 * slice_len).unwrap();
+        for (height, slice) in heights.iter_mut().zip(block.chunks_exact(slice_len)) {
+            height
+                .slice_mut(span)
+                .assign(&read_array(path, slice, nz_proc)?);
+        }
+    }
 
-    let viscous = read_array(viscous_floats, nz)?;
-    let log_law = read_array(log_law_floats, nz)?;
-    let freestream = read_array(freestream_floats, nz)?;
+    let mut heights = heights.into_iter();
 
     Ok(ProbeFile {
-        viscous,
-        log_law,
-        freestream,
+        viscous: heights.next().unwrap(),
+        log_law: heights.next().unwrap(),
+        freestream: heights.next().unwrap(),
     })
 }
 
-/// from a buffer of bytes that is
+/// from a buffer of floats (rho, u, v, w at each of the nz points) to a `(4, nz)` array
 #[inline(always)]
-fn read_array(buffer: &[f64], nz: usize) -> Result<Array2, ProbeBinaryError> {
+fn read_array(path: &Path, buffer: &[f64], nz: usize) -> Result<Array2, ProbeBinaryError> {
     // check that the buffer is the length that we expect it to be
     if buffer.len() != nz * 4 {
-        let diff = ((buffer.len() as isize) - (nz * 4) as isize).abs() as usize;
-        return Err(Length::new(nz, buffer.len(), nz * 4, diff).into());
+        return Err(Length::from_floats(path, nz, buffer.len(), nz * 4).into());
     }
 
-    let mut arr = Array2::zeros((4, nz));
+    // the buffer is (nz, 4) in row major order, so viewing it requires no copy
+    let view = ndarray::ArrayView2::from_shape((nz, 4), buffer)
+        .expect("buffer length was checked against nz");
 
-    buffer
-        .chunks(4)
-        .into_iter()
-        .enumerate()
-        .for_each(|(idx, chunk)| {
-            // iterate through each of the probe points of information
-            for information_idx in 0..4 {
-                *arr.get_mut((information_idx, idx)).unwrap() = chunk[information_idx]
-            }
-        });
-
-    Ok(arr)
+    Ok(view.t().to_owned())
 }
 
 #[test]
@@ -161,5 +206,31 @@ fn read_array(buffer: &[f64], nz: usize) -> Result<Array2, ProbeBinaryError> {
 fn parse_probe_binary() {
     let file = PathBuf::from("./static/span_probe_example.binary");
     let nz = 150;
-    parse_binary_file(&file, nz).unwrap();
+    parse_binary_file(&file, nz, 1).unwrap();
+}
+
+#[test]
+// blocks from processes along z are placed side by side along the span
+fn probe_blocks_from_z_processes() {
+    let nz = 4;
+    // every value is the index of the process along z that wrote it
+    let floats = (0..2)
+        .flat_map(|z_proc| vec![z_proc as f64; 3 * 2 * 4])
+        .collect::<Vec<_>>();
+    let bytes = floats
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    let path = std::env::temp_dir().join(format!(
+        "streams_utils_probe_blocks_{}.binary",
+        std::process::id()
+    ));
+    fs::write(&path, bytes).unwrap();
+
+    let probe = parse_binary_file(&path, nz, 2).unwrap();
+    fs::remove_file(&path).ok();
+
+    assert_eq!(probe.log_law.row(0).to_vec(), [0., 0., 1., 1.]);
+    assert_eq!(probe.freestream.row(3).to_vec(), [0., 0., 1., 1.]);
 }
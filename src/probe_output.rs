@@ -0,0 +1,356 @@
+//! writers for the probe data of a single probe in each of the supported output formats
+//!
+//! every format stores the same arrays as [`ProbeFileAllTimesteps`], shaped
+//! `(time, variable, z)` where the variables are rho, u, v, w.
+
+use crate::prelude::*;
+use crate::probe::ProbeLocation;
+use crate::probe_binary::ProbeFileAllTimesteps;
+
+/// names of the entries along the variable dimension
+const VARIABLES: &str = "rho,u,v,w";
+
+/// names of the dimensions of every probe array, following the CF conventions
+const DIMENSIONS: [&str; 3] = ["time", "variable", "z"];
+
+/// the data of one probe along with the metadata written next to it
+pub(crate) struct ProbeOutput<'a> {
+    pub(crate) data: ProbeFileAllTimesteps,
+    /// solver step of each timestep
+    pub(crate) steps: Vec<usize>,
+    pub(crate) location: ProbeLocation,
+    /// physical z coordinate of each probe point, if the mesh is known
+    pub(crate) z_coordinates: Option<&'a [f64]>,
+    pub(crate) config: &'a Config,
+}
+
+impl ProbeOutput<'_> {
+    /// write the probe to `path` in the given format
+    pub(crate) fn write(self, path: &Path, format: cli::ProbeFormat) -> Result<(), Error> {
+        match format {
+            cli::ProbeFormat::Mat => self.write_mat(path),
+            cli::ProbeFormat::Hdf5 => self.write_hdf5(path),
+            cli::ProbeFormat::Netcdf => self.write_netcdf(path),
+            cli::ProbeFormat::Npz => self.write_npz(path),
+        }
+    }
+
+    fn arrays(&self) -> [(&'static str, &Array3); 3] {
+        [
+            ("viscous", &self.data.viscous),
+            ("log_law", &self.data.log_law),
+            ("freestream", &self.data.freestream),
+        ]
+    }
+
+    fn write_mat(self, path: &Path) -> Result<(), Error> {
+        let writer = io::BufWriter::new(
+            fs::File::create(path).map_err(|e| FileError::new(path.to_owned(), e))?,
+        );
+
+        let output = ProbeMatFile {
+            viscous: self.data.viscous,
+            log_law: self.data.log_law,
+            freestream: self.data.freestream,
+            steps: self.steps.iter().map(|step| *step as f64).collect(),
+            x_index: vec![self.location.x_index],
+            z_index: vec![self.location.z_index],
+            x: vec![self.location.x],
+            z: vec![self.location.z],
+        };
+
+        mat5::MatFile::write_contents(&output, writer)?;
+
+        Ok(())
+    }
+
+    /// one chunked, compressed dataset per array, with the location and config stored as
+    /// attributes of the file
+    fn write_hdf5(&self, path: &Path) -> Result<(), Error> {
+        let file = hdf5::File::create(path)?;
+
+        for (name, array) in self.arrays() {
+            let (_, variables, nz) = array.dim();
+
+            let dataset = file
+                .new_dataset::<f64>()
+                .shape(array.dim())
+                .chunk((1, variables, nz))
+                .deflate(utils::COMPRESSION_LEVEL)
+                .create(name)?;
+            dataset.write(array)?;
+
+            // hdf5 has no named dimensions, so they are stored as in the netcdf output
+            let dimensions: hdf5::types::VarLenUnicode = DIMENSIONS.join(",").parse()?;
+            dataset
+                .new_attr::<hdf5::types::VarLenUnicode>()
+                .shape(())
+                .create("dimensions")?
+                .write_scalar(&dimensions)?;
+        }
+
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| *step as u64)
+            .collect::<ndarray::Array1<u64>>();
+        file.new_dataset_builder()
+            .with_data(&steps)
+            .create("step")?;
+
+        if let Some(z) = self.z_coordinates {
+            let z = ndarray::Array1::from(z.to_vec());
+            file.new_dataset_builder().with_data(&z).create("z")?;
+        }
+
+        let location = [
+            ("x_index", self.location.x_index),
+            ("z_index", self.location.z_index),
+            ("x", self.location.x),
+            ("z", self.location.z),
+        ];
+
+        for (name, value) in location {
+            file.new_attr::<f64>()
+                .shape(())
+                .create(name)?
+                .write_scalar(&value)?;
+        }
+
+        let strings = [
+            ("variables", VARIABLES.to_string()),
+            ("config", serde_json::to_string(self.config)?),
+        ];
+
+        for (name, value) in strings {
+            let value: hdf5::types::VarLenUnicode = value.parse()?;
+            file.new_attr::<hdf5::types::VarLenUnicode>()
+                .shape(())
+                .create(name)?
+                .write_scalar(&value)?;
+        }
+
+        Ok(())
+    }
+
+    /// one array per field. The location is stored as single element arrays, as in the .mat
+    /// output
+    fn write_npz(&self, path: &Path) -> Result<(), Error> {
+        let mut npz = crate::npy::NpzWriter::create(path)?;
+
+        for (name, array) in self.arrays() {
+            npz.add_array(name, array)?;
+        }
+
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| *step as f64)
+            .collect::<ndarray::Array1<f64>>();
+        npz.add_array("steps", &steps)?;
+
+        let location = [
+            ("x_index", self.location.x_index),
+            ("z_index", self.location.z_index),
+            ("x", self.location.x),
+            ("z", self.location.z),
+        ];
+
+        for (name, value) in location {
+            npz.add_array(name, &ndarray::arr1(&[value]))?;
+        }
+
+        npz.finish()
+    }
+
+    /// NetCDF-4 file with `time`, `variable` and `z` dimensions, following the CF conventions
+    /// for coordinate variables
+    #[cfg(feature = "netcdf")]
+    fn write_netcdf(&self, path: &Path) -> Result<(), Error> {
+        let mut file = netcdf::create(path)?;
+
+        let nz = self.data.viscous.dim().2;
+
+        let [time_dim, variable_dim, z_dim] = DIMENSIONS;
+        file.add_dimension(time_dim, self.steps.len())?;
+        file.add_dimension(variable_dim, 4)?;
+        file.add_dimension(z_dim, nz)?;
+
+        file.add_attribute("Conventions", "CF-1.8")?;
+        file.add_attribute("config", serde_json::to_string(self.config)?)?;
+        file.add_attribute("probe_x_index", self.location.x_index)?;
+        file.add_attribute("probe_z_index", self.location.z_index)?;
+        file.add_attribute("probe_x", self.location.x)?;
+        file.add_attribute("probe_z", self.location.z)?;
+
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| *step as u64)
+            .collect::<Vec<_>>();
+        let mut step = file.add_variable::<u64>("step", &[time_dim])?;
+        step.add_attribute("long_name", "solver step")?;
+        step.put_values(&steps, None, None)?;
+
+        if let Some(z) = self.z_coordinates {
+            let mut z_var = file.add_variable::<f64>("z", &[z_dim])?;
+            z_var.add_attribute("long_name", "spanwise coordinate")?;
+            z_var.add_attribute("axis", "Z")?;
+            z_var.put_values(z, None, None)?;
+        }
+
+        for (name, array) in self.arrays() {
+            let mut var = file.add_variable::<f64>(name, &DIMENSIONS)?;
+            var.chunking(&[1, 4, nz])?;
+            var.compression(utils::COMPRESSION_LEVEL as i32)?;
+            var.add_attribute("variables", VARIABLES)?;
+
+            let values = array.as_standard_layout();
+            var.put_values(
+                values.as_slice().expect("array is in standard layout"),
+                None,
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "netcdf"))]
+    fn write_netcdf(&self, _path: &Path) -> Result<(), Error> {
+        Err(Error::NetcdfUnsupported)
+    }
+}
+
+/// probe data of every timestep, along with where the probe is in the domain
+///
+/// see [`ProbeFileAllTimesteps`] for the layout of the data arrays.
+/// The location fields are NaN if they could not be determined.
+#[derive(mat5::MatFile)]
+struct ProbeMatFile {
+    viscous: Array3,
+    log_law: Array3,
+    freestream: Array3,
+    /// solver step of each timestep
+    steps: Vec<f64>,
+    /// (1 indexed) grid point index of the probe in x
+    x_index: Vec<f64>,
+    /// (1 indexed) grid point index of the probe in z
+    z_index: Vec<f64>,
+    /// physical x coordinate of the probe
+    x: Vec<f64>,
+    /// physical z coordinate of the probe
+    z: Vec<f64>,
+}
+
+#[cfg(test)]
+/// a probe with 3 timesteps of 2 points in z, where every value is distinct
+fn sample_output(config: &Config) -> ProbeOutput<'_> {
+    let array = |offset: f64| {
+        Array3::from_shape_fn((3, 4, 2), |(t, var, k)| {
+            offset + t as f64 * 100. + var as f64 * 10. + k as f64
+        })
+    };
+
+    ProbeOutput {
+        data: ProbeFileAllTimesteps {
+            viscous: array(0.),
+            log_law: array(1000.),
+            freestream: array(2000.),
+        },
+        steps: vec![100, 200, 300],
+        location: ProbeLocation {
+            x_index: 5.,
+            z_index: 2.,
+            x: 0.25,
+            z: 0.5,
+        },
+        z_coordinates: Some(&[0., 0.5]),
+        config,
+    }
+}
+
+#[test]
+fn hdf5_probe_round_trip() {
+    let config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    let path = std::env::temp_dir().join(format!("streams_utils_probe_{}.h5", std::process::id()));
+
+    sample_output(&config)
+        .write(&path, cli::ProbeFormat::Hdf5)
+        .unwrap();
+
+    let file = hdf5::File::open(&path).unwrap();
+    let string_attr = |attr: hdf5::Attribute| {
+        attr.read_scalar::<hdf5::types::VarLenUnicode>()
+            .unwrap()
+            .to_string()
+    };
+
+    for (name, offset) in [("viscous", 0.), ("log_law", 1000.), ("freestream", 2000.)] {
+        let dataset = file.dataset(name).unwrap();
+        let array: Array3 = dataset.read().unwrap();
+        assert_eq!(array.dim(), (3, 4, 2), "{name}");
+        assert_eq!(array[(2, 3, 1)], offset + 231., "{name}");
+
+        let dimensions = string_attr(dataset.attr("dimensions").unwrap());
+        assert_eq!(dimensions, "time,variable,z");
+    }
+
+    let steps = file.dataset("step").unwrap().read_raw::<u64>().unwrap();
+    assert_eq!(steps, [100, 200, 300]);
+    let z = file.dataset("z").unwrap().read_raw::<f64>().unwrap();
+    assert_eq!(z, [0., 0.5]);
+
+    for (name, value) in [("x_index", 5.), ("z_index", 2.), ("x", 0.25), ("z", 0.5)] {
+        let attr = file.attr(name).unwrap().read_scalar::<f64>().unwrap();
+        assert_eq!(attr, value, "{name}");
+    }
+
+    assert_eq!(string_attr(file.attr("variables").unwrap()), VARIABLES);
+    let stored: Config = serde_json::from_str(&string_attr(file.attr("config").unwrap())).unwrap();
+    assert_eq!(
+        serde_json::to_value(stored).unwrap(),
+        serde_json::to_value(&config).unwrap()
+    );
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "netcdf")]
+#[test]
+fn netcdf_probe_dimensions() {
+    let config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    let path = std::env::temp_dir().join(format!("streams_utils_probe_{}.nc", std::process::id()));
+
+    sample_output(&config)
+        .write(&path, cli::ProbeFormat::Netcdf)
+        .unwrap();
+
+    let file = netcdf::open(&path).unwrap();
+
+    let dimensions = file
+        .dimensions()
+        .map(|dimension| (dimension.name(), dimension.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dimensions,
+        [("time".into(), 3), ("variable".into(), 4), ("z".into(), 2)]
+    );
+
+    let viscous = file.variable("viscous").unwrap();
+    let names = viscous
+        .dimensions()
+        .iter()
+        .map(|dimension| dimension.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, DIMENSIONS);
+
+    let steps = file
+        .variable("step")
+        .unwrap()
+        .values::<u64>(None, None)
+        .unwrap();
+    assert_eq!(steps.iter().copied().collect::<Vec<_>>(), [100, 200, 300]);
+
+    fs::remove_file(&path).unwrap();
+}
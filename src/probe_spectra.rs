@@ -0,0 +1,452 @@
+//! spectral analysis of probe time series
+//!
+//! every probe records `(timestep, 4, nz)` arrays (rho, u, v, w across the span) at three wall
+//! normal heights. From these we compute frequency spectra (Welch's method), spanwise wavenumber
+//! spectra, two-point correlations along the span, and premultiplied versions of both spectra.
+
+use crate::prelude::*;
+use crate::probe_binary::ProbeFileAllTimesteps;
+
+use ndarray::s;
+use ndarray::ArrayView1;
+use ndarray::ArrayView2;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// the number of heights (viscous, log law, freestream) that a probe samples
+const NUM_HEIGHTS: usize = 3;
+
+/// the number of variables (rho, u, v, w) written at every probe point
+const NUM_VARIABLES: usize = 4;
+
+/// spectra computed for a single probe
+///
+/// every 3 dimensional array is indexed as
+///
+/// | Dimension | Data chosen |
+/// |-----------|-------------|
+/// | 1st       | Probe height: viscous, log law, freestream |
+/// | 2nd       | Data type. This dimension should have 4 indicies for : rho, u, v, w |
+/// | 3rd       | Frequency, wavenumber, or separation (matching the vector of the same name) |
+#[derive(mat5::MatFile)]
+struct ProbeSpectra {
+    /// physical frequencies of `psd`
+    frequency: Vec<f64>,
+    /// one sided power spectral density from Welch's method, averaged over every span point
+    psd: Array3,
+    /// `frequency * psd`
+    premultiplied_psd: Array3,
+    /// spanwise wavenumbers `k_z` of `spanwise_spectrum`
+    wavenumber: Vec<f64>,
+    /// one sided spanwise energy spectrum, averaged over every timestep
+    spanwise_spectrum: Array3,
+    /// `k_z * spanwise_spectrum`
+    premultiplied_spanwise_spectrum: Array3,
+    /// spanwise separations `dz` of `correlation`
+    separation: Vec<f64>,
+    /// two point correlation coefficient along the span
+    correlation: Array3,
+}
+
+/// for a given configuration file and probe directory, compute the spectra of every probe
+/// and write them to .mat files
+pub(crate) fn probe_spectra(args: cli::ProbeSpectra) -> anyhow::Result<()> {
+    let config = Config::load(&args.config, &args.overrides)?;
+
+    let sample_interval = sample_interval(&config, args.dt)?;
+
+    if !(0.0..1.0).contains(&args.overlap) {
+        anyhow::bail!(
+            "--overlap must be in the range [0, 1), got {}",
+            args.overlap
+        );
+    }
+
+    let paths = crate::probe::probe_paths(&args.probe_directory)?;
+    let grouping = crate::probe::group_probes_by_number(paths.into_iter(), false)?;
+
+    // create the output directory if it does not exist
+    if !args.output_directory.exists() {
+        std::fs::create_dir(&args.output_directory).with_context(|| {
+            format!(
+                "failed to create output directory {}",
+                args.output_directory.display()
+            )
+        })?;
+    }
+
+    for (probe_number, probe_info) in grouping.groups() {
+        if probe_info.is_empty() {
+            continue;
+        }
+
+        println!(
+            "computing spectra for probe {probe_number} ({} timesteps)",
+            probe_info.len()
+        );
+
+        let paths = probe_info
+            .iter()
+            .map(|info| info.path.clone())
+            .collect::<Vec<_>>();
+
+        let (probe_data, _) = crate::probe_binary::read_file_group(
+            &paths,
+            config.z_divisions,
+            config.mpi_z_split,
+            false,
+        )?;
+
+        let spectra = ProbeSpectra::from_probe_data(
+            &probe_data,
+            sample_interval,
+            config.z_length,
+            args.segment_length,
+            args.overlap,
+        );
+
+        let path = args
+            .output_directory
+            .join(format!("probe_{probe_number}_spectra.mat"));
+        let writer = io::BufWriter::new(
+            fs::File::create(&path)
+                .with_context(|| format!("failed to create spectra file {}", path.display()))?,
+        );
+        mat5::MatFile::write_contents(&spectra, writer)?;
+    }
+
+    Ok(())
+}
+
+/// physical time between two consecutive probe files
+fn sample_interval(config: &Config, dt: Option<f64>) -> anyhow::Result<f64> {
+    if config.probe_io_steps == 0 {
+        anyhow::bail!(
+            "probe_io_steps is 0 in the config file: no probe data was written by the solver"
+        );
+    }
+
+    let dt = config.timestep(dt)?;
+
+    Ok(dt * config.probe_io_steps as f64)
+}
+
+impl ProbeSpectra {
+    fn from_probe_data(
+        data: &ProbeFileAllTimesteps,
+        sample_interval: f64,
+        z_length: f64,
+        segment_length: usize,
+        overlap: f64,
+    ) -> Self {
+        let (num_timesteps, _, nz) = data.viscous.dim();
+
+        let segment_length = segment_length.min(num_timesteps).max(1);
+        let dz = z_length / nz as f64;
+
+        let frequency = welch_frequencies(segment_length, sample_interval);
+        let wavenumber = spanwise_wavenumbers(nz, z_length);
+        let separation = (0..=nz / 2).map(|j| j as f64 * dz).collect::<Vec<_>>();
+
+        let mut psd = Array3::zeros((NUM_HEIGHTS, NUM_VARIABLES, frequency.len()));
+        let mut spanwise_spectrum = Array3::zeros((NUM_HEIGHTS, NUM_VARIABLES, wavenumber.len()));
+        let mut correlation = Array3::zeros((NUM_HEIGHTS, NUM_VARIABLES, separation.len()));
+
+        let mut planner = FftPlanner::new();
+
+        for (height_idx, height) in [&data.viscous, &data.log_law, &data.freestream]
+            .into_iter()
+            .enumerate()
+        {
+            for variable in 0..NUM_VARIABLES {
+                let fluctuations = temporal_fluctuations(height.slice(s![.., variable, ..]));
+
+                // average the frequency spectrum of every point along the span
+                let mut psd_avg = vec![0.; frequency.len()];
+                for k in 0..nz {
+                    let point_psd = welch(
+                        &mut planner,
+                        fluctuations.slice(s![.., k]),
+                        sample_interval,
+                        segment_length,
+                        overlap,
+                    );
+                    psd_avg
+                        .iter_mut()
+                        .zip(point_psd)
+                        .for_each(|(avg, value)| *avg += value / nz as f64);
+                }
+
+                let (energy, corr) =
+                    spanwise_statistics(&mut planner, fluctuations.view(), z_length);
+
+                psd.slice_mut(s![height_idx, variable, ..])
+                    .assign(&ArrayView1::from(psd_avg.as_slice()));
+                spanwise_spectrum
+                    .slice_mut(s![height_idx, variable, ..])
+                    .assign(&ArrayView1::from(energy.as_slice()));
+                correlation
+                    .slice_mut(s![height_idx, variable, ..])
+                    .assign(&ArrayView1::from(corr.as_slice()));
+            }
+        }
+
+        let premultiplied_psd = premultiply(&psd, &frequency);
+        let premultiplied_spanwise_spectrum = premultiply(&spanwise_spectrum, &wavenumber);
+
+        Self {
+            frequency,
+            psd,
+            premultiplied_psd,
+            wavenumber,
+            spanwise_spectrum,
+            premultiplied_spanwise_spectrum,
+            separation,
+            correlation,
+        }
+    }
+}
+
+/// remove the time average at every span location of a `(timestep, nz)` array
+fn temporal_fluctuations(series: ArrayView2<f64>) -> Array2 {
+    let mut fluctuations = series.to_owned();
+
+    for mut column in fluctuations.columns_mut() {
+        let mean = column.mean().unwrap_or(0.);
+        column.mapv_inplace(|x| x - mean);
+    }
+
+    fluctuations
+}
+
+/// multiply every spectrum in the last axis of `spectra` by its abscissa
+fn premultiply(spectra: &Array3, abscissa: &[f64]) -> Array3 {
+    let mut out = spectra.clone();
+
+    for mut lane in out.lanes_mut(ndarray::Axis(2)) {
+        lane.iter_mut()
+            .zip(abscissa)
+            .for_each(|(value, x)| *value *= x);
+    }
+
+    out
+}
+
+/// frequencies of the one sided spectrum returned from [`welch`]
+fn welch_frequencies(segment_length: usize, sample_interval: f64) -> Vec<f64> {
+    let df = 1. / (segment_length as f64 * sample_interval);
+    (0..=segment_length / 2).map(|k| k as f64 * df).collect()
+}
+
+/// spanwise wavenumbers of the one sided spectrum returned from [`spanwise_statistics`]
+fn spanwise_wavenumbers(nz: usize, z_length: f64) -> Vec<f64> {
+    let dk = 2. * std::f64::consts::PI / z_length;
+    (0..=nz / 2).map(|m| m as f64 * dk).collect()
+}
+
+/// periodic hann window of a given length, which is the usual window for spectral estimates
+fn hann(length: usize) -> Vec<f64> {
+    if length == 1 {
+        return vec![1.];
+    }
+
+    (0..length)
+        .map(|n| {
+            let phase = 2. * std::f64::consts::PI * n as f64 / length as f64;
+            0.5 * (1. - phase.cos())
+        })
+        .collect()
+}
+
+/// one sided power spectral density of a time series with Welch's method
+///
+/// segments are hann windowed and have their mean removed. The result is a density:
+/// summing `psd * df` recovers the variance of the signal.
+fn welch(
+    planner: &mut FftPlanner<f64>,
+    series: ArrayView1<f64>,
+    sample_interval: f64,
+    segment_length: usize,
+    overlap: f64,
+) -> Vec<f64> {
+    let window = hann(segment_length);
+    let window_power: f64 = window.iter().map(|w| w * w).sum();
+    let sample_frequency = 1. / sample_interval;
+
+    let step = (segment_length - (overlap * segment_length as f64).round() as usize).max(1);
+
+    let fft = planner.plan_fft_forward(segment_length);
+    let mut buffer = vec![Complex::new(0., 0.); segment_length];
+    let mut psd = vec![0.; segment_length / 2 + 1];
+    let mut num_segments = 0;
+
+    let mut start = 0;
+    while start + segment_length <= series.len() {
+        let segment = series.slice(s![start..start + segment_length]);
+        let mean = segment.mean().unwrap_or(0.);
+
+        buffer
+            .iter_mut()
+            .zip(segment.iter().zip(&window))
+            .for_each(|(value, (x, w))| *value = Complex::new((x - mean) * w, 0.));
+
+        fft.process(&mut buffer);
+
+        psd.iter_mut()
+            .zip(&buffer)
+            .for_each(|(p, value)| *p += value.norm_sqr());
+
+        num_segments += 1;
+        start += step;
+    }
+
+    let scale = 1. / (sample_frequency * window_power * num_segments.max(1) as f64);
+
+    psd.iter_mut().enumerate().for_each(|(k, p)| {
+        *p *= scale;
+
+        // fold the negative frequencies into the positive ones. The zero and nyquist
+        // frequencies have no counterpart
+        let is_nyquist = segment_length % 2 == 0 && k == segment_length / 2;
+        if k != 0 && !is_nyquist {
+            *p *= 2.;
+        }
+    });
+
+    psd
+}
+
+/// one sided spanwise energy spectrum and two point correlation coefficient of a
+/// `(timestep, nz)` array of fluctuations, both averaged over time
+///
+/// the span is periodic in the solver, so the correlation is computed as the inverse transform
+/// of the two sided spectrum. The energy spectrum is a density: summing `spectrum * dk` recovers
+/// the variance of the signal.
+fn spanwise_statistics(
+    planner: &mut FftPlanner<f64>,
+    fluctuations: ArrayView2<f64>,
+    z_length: f64,
+) -> (Vec<f64>, Vec<f64>) {
+    let (num_timesteps, nz) = fluctuations.dim();
+
+    let fft = planner.plan_fft_forward(nz);
+    let ifft = planner.plan_fft_inverse(nz);
+
+    let mut buffer = vec![Complex::new(0., 0.); nz];
+    let mut two_sided = vec![0.; nz];
+
+    for row in fluctuations.rows() {
+        buffer
+            .iter_mut()
+            .zip(row)
+            .for_each(|(value, x)| *value = Complex::new(*x, 0.));
+
+        fft.process(&mut buffer);
+
+        two_sided
+            .iter_mut()
+            .zip(&buffer)
+            .for_each(|(p, value)| *p += value.norm_sqr() / (nz * nz * num_timesteps) as f64);
+    }
+
+    let dk = 2. * std::f64::consts::PI / z_length;
+    let spectrum = (0..=nz / 2)
+        .map(|m| {
+            let is_nyquist = nz % 2 == 0 && m == nz / 2;
+            let folded = if m == 0 || is_nyquist {
+                two_sided[m]
+            } else {
+                two_sided[m] + two_sided[nz - m]
+            };
+
+            folded / dk
+        })
+        .collect();
+
+    buffer
+        .iter_mut()
+        .zip(&two_sided)
+        .for_each(|(value, p)| *value = Complex::new(*p, 0.));
+    ifft.process(&mut buffer);
+
+    let variance = buffer[0].re;
+    let correlation = buffer
+        .iter()
+        .take(nz / 2 + 1)
+        .map(|value| {
+            if variance > 0. {
+                value.re / variance
+            } else {
+                0.
+            }
+        })
+        .collect();
+
+    (spectrum, correlation)
+}
+
+#[test]
+/// a pure sinusoid should have a PSD peak at its frequency that integrates to its variance
+fn welch_sinusoid_peak() {
+    let sample_interval = 0.01;
+    let frequency = 12.5;
+    let amplitude = 2.;
+
+    let series = (0..4096)
+        .map(|n| {
+            let t = n as f64 * sample_interval;
+            amplitude * (2. * std::f64::consts::PI * frequency * t).sin()
+        })
+        .collect::<Vec<_>>();
+
+    let mut planner = FftPlanner::new();
+    let segment_length = 256;
+    let psd = welch(
+        &mut planner,
+        ArrayView1::from(series.as_slice()),
+        sample_interval,
+        segment_length,
+        0.5,
+    );
+    let frequencies = welch_frequencies(segment_length, sample_interval);
+
+    let (peak_idx, _) = psd
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .unwrap();
+    assert!((frequencies[peak_idx] - frequency).abs() < 1e-10);
+
+    let df = frequencies[1] - frequencies[0];
+    let variance: f64 = psd.iter().map(|p| p * df).sum();
+    assert!((variance - amplitude * amplitude / 2.).abs() < 1e-2);
+}
+
+#[test]
+/// a single spanwise wave should have all of its energy at one wavenumber and a cosine
+/// shaped correlation
+fn spanwise_single_mode() {
+    let nz = 64;
+    let z_length = 4.;
+    let mode = 3;
+
+    let data = Array2::from_shape_fn((10, nz), |(t, k)| {
+        let z = k as f64 * z_length / nz as f64;
+        let phase = 0.1 * t as f64;
+        (2. * std::f64::consts::PI * mode as f64 * z / z_length + phase).cos()
+    });
+
+    let mut planner = FftPlanner::new();
+    let (spectrum, correlation) = spanwise_statistics(&mut planner, data.view(), z_length);
+    let wavenumbers = spanwise_wavenumbers(nz, z_length);
+    let dk = wavenumbers[1];
+
+    assert!((spectrum[mode] * dk - 0.5).abs() < 1e-10);
+    assert!((spectrum.iter().sum::<f64>() * dk - 0.5).abs() < 1e-10);
+
+    let dz = z_length / nz as f64;
+    for (j, value) in correlation.iter().enumerate() {
+        let expected = (2. * std::f64::consts::PI * mode as f64 * j as f64 * dz / z_length).cos();
+        assert!((value - expected).abs() < 1e-10);
+    }
+}
@@ -0,0 +1,541 @@
+//! rasterize 2D fields on a (non-uniform) rectilinear mesh to PNG images, with axes, a colorbar
+//! and a text annotation
+//!
+//! everything is drawn with a built in 5x7 bitmap font so that no system fonts or plotting
+//! libraries are required.
+
+use crate::prelude::*;
+use ndarray::ArrayView2;
+
+type Rgb = [u8; 3];
+
+const WHITE: Rgb = [255, 255, 255];
+const BLACK: Rgb = [0, 0, 0];
+/// color of values that are NaN
+const MISSING: Rgb = [128, 128, 128];
+
+/// scale factor of the 5x7 font
+const FONT_SCALE: usize = 2;
+/// horizontal space taken by every character, including the gap to the next one
+const CHAR_ADVANCE: usize = 6 * FONT_SCALE;
+const CHAR_HEIGHT: usize = 7 * FONT_SCALE;
+
+const MARGIN_LEFT: usize = 100;
+const MARGIN_RIGHT: usize = 140;
+const MARGIN_TOP: usize = 40;
+const MARGIN_BOTTOM: usize = 60;
+const TICK_LENGTH: usize = 6;
+const COLORBAR_WIDTH: usize = 20;
+
+/// the smallest frame that leaves room for the plot inside the margins
+pub(crate) const MIN_WIDTH: u32 = 400;
+pub(crate) const MIN_HEIGHT: u32 = 200;
+
+/// an RGB image
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Rgb) -> Self {
+        let pixels = background
+            .iter()
+            .copied()
+            .cycle()
+            .take(width * height * 3)
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            let idx = (y * self.width + x) * 3;
+            self.pixels[idx..idx + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..y + height {
+            for col in x..x + width {
+                self.set(col, row, color);
+            }
+        }
+    }
+
+    /// draw a one pixel wide outline of a rectangle
+    fn outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        self.fill_rect(x, y, width, 1, color);
+        self.fill_rect(x, y + height - 1, width, 1, color);
+        self.fill_rect(x, y, 1, height, color);
+        self.fill_rect(x + width - 1, y, 1, height, color);
+    }
+
+    /// draw `text` with its top left corner at `(x, y)`
+    fn text(&mut self, x: usize, y: usize, text: &str, color: Rgb) {
+        for (idx, character) in text.chars().enumerate() {
+            let glyph = glyph(character);
+            let left = x + idx * CHAR_ADVANCE;
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..5 {
+                    if bits & (0x10 >> col) != 0 {
+                        self.fill_rect(
+                            left + col * FONT_SCALE,
+                            y + row * FONT_SCALE,
+                            FONT_SCALE,
+                            FONT_SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("failed to create image {}", path.display()))?;
+
+        let mut encoder = png::Encoder::new(
+            io::BufWriter::new(file),
+            self.width as u32,
+            self.height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .with_context(|| format!("failed to write image {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// width in pixels of `text` drawn with [`Canvas::text`]
+fn text_width(text: &str) -> usize {
+    text.chars().count() * CHAR_ADVANCE
+}
+
+/// evenly spaced control points of each colormap, sampled from matplotlib
+fn control_points(colormap: cli::Colormap) -> &'static [Rgb] {
+    match colormap {
+        cli::Colormap::Viridis => &[
+            [68, 1, 84],
+            [71, 44, 122],
+            [59, 81, 139],
+            [44, 113, 142],
+            [33, 144, 141],
+            [39, 173, 129],
+            [92, 200, 99],
+            [170, 220, 50],
+            [253, 231, 37],
+        ],
+        cli::Colormap::Magma => &[
+            [0, 0, 4],
+            [28, 16, 68],
+            [79, 18, 123],
+            [129, 37, 129],
+            [181, 54, 122],
+            [229, 80, 100],
+            [251, 135, 97],
+            [254, 194, 135],
+            [252, 253, 191],
+        ],
+        cli::Colormap::Inferno => &[
+            [0, 0, 4],
+            [31, 12, 72],
+            [85, 15, 109],
+            [136, 34, 106],
+            [186, 54, 85],
+            [227, 89, 51],
+            [249, 140, 10],
+            [249, 201, 50],
+            [252, 255, 164],
+        ],
+        cli::Colormap::Gray => &[[0, 0, 0], [255, 255, 255]],
+        cli::Colormap::RdBu => &[
+            [103, 0, 31],
+            [178, 24, 43],
+            [214, 96, 77],
+            [244, 165, 130],
+            [253, 219, 199],
+            [247, 247, 247],
+            [209, 229, 240],
+            [146, 197, 222],
+            [67, 147, 195],
+            [33, 102, 172],
+            [5, 48, 97],
+        ],
+        cli::Colormap::Coolwarm => &[
+            [59, 76, 192],
+            [98, 130, 234],
+            [141, 176, 254],
+            [184, 208, 249],
+            [221, 221, 221],
+            [245, 196, 173],
+            [244, 154, 123],
+            [222, 96, 77],
+            [180, 4, 38],
+        ],
+    }
+}
+
+/// whether the colormap is meant for data centered on zero
+pub(crate) fn is_diverging(colormap: cli::Colormap) -> bool {
+    matches!(colormap, cli::Colormap::RdBu | cli::Colormap::Coolwarm)
+}
+
+/// the color of `fraction` (clamped to 0..=1) along a colormap
+fn sample_colormap(points: &[Rgb], fraction: f64) -> Rgb {
+    if fraction.is_nan() {
+        return MISSING;
+    }
+
+    let position = fraction.clamp(0., 1.) * (points.len() - 1) as f64;
+    let lower = (position.floor() as usize).min(points.len() - 2);
+    let weight = position - lower as f64;
+
+    let mut color = [0; 3];
+    for (channel, out) in color.iter_mut().enumerate() {
+        let a = points[lower][channel] as f64;
+        let b = points[lower + 1][channel] as f64;
+        *out = (a + (b - a) * weight).round() as u8;
+    }
+
+    color
+}
+
+/// index of the grid point before `coordinate` and the weight of the grid point after it, for
+/// linear interpolation along an axis of sorted coordinates
+fn interpolation_weights(grid: &[f64], coordinate: f64) -> (usize, f64) {
+    if grid.len() < 2 {
+        return (0, 0.);
+    }
+
+    let upper = grid
+        .partition_point(|value| *value <= coordinate)
+        .clamp(1, grid.len() - 1);
+    let lower = upper - 1;
+
+    let weight = (coordinate - grid[lower]) / (grid[upper] - grid[lower]);
+
+    (lower, weight.clamp(0., 1.))
+}
+
+/// roughly `target` evenly spaced, round numbers between `min` and `max`, along with the number
+/// of decimal places needed to print them
+fn nice_ticks(min: f64, max: f64, target: usize) -> (Vec<f64>, usize) {
+    if !min.is_finite() || !max.is_finite() || max <= min {
+        return (vec![min], 0);
+    }
+
+    let raw_step = (max - min) / target as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10. * magnitude);
+
+    let decimals = (-step.log10().floor()).max(0.) as usize;
+
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    // adding zero turns -0.0 into 0.0 so that it is not printed with a sign
+    let ticks = (first..=last).map(|idx| idx as f64 * step + 0.).collect();
+
+    (ticks, decimals)
+}
+
+/// how every frame of an animation is drawn: the mesh, colormap and limits are shared between
+/// frames so that the interpolation weights are only computed once
+pub(crate) struct FieldPlot {
+    width: usize,
+    height: usize,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    colormap: &'static [Rgb],
+    limits: (f64, f64),
+    title: String,
+    /// interpolation weights of every column of the plot area
+    columns: Vec<(usize, f64)>,
+    /// interpolation weights of every row of the plot area, from the top
+    rows: Vec<(usize, f64)>,
+}
+
+impl FieldPlot {
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        x: Vec<f64>,
+        y: Vec<f64>,
+        colormap: cli::Colormap,
+        limits: (f64, f64),
+        title: String,
+    ) -> Self {
+        let width = width.max(MIN_WIDTH) as usize;
+        let height = height.max(MIN_HEIGHT) as usize;
+
+        let plot_width = width - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_height = height - MARGIN_TOP - MARGIN_BOTTOM;
+
+        let (x_min, x_max) = (x[0], x[x.len() - 1]);
+        let (y_min, y_max) = (y[0], y[y.len() - 1]);
+
+        // pixels are sampled at their centers
+        let columns = (0..plot_width)
+            .map(|col| {
+                let fraction = (col as f64 + 0.5) / plot_width as f64;
+                interpolation_weights(&x, x_min + fraction * (x_max - x_min))
+            })
+            .collect();
+        let rows = (0..plot_height)
+            .map(|row| {
+                let fraction = (row as f64 + 0.5) / plot_height as f64;
+                interpolation_weights(&y, y_max - fraction * (y_max - y_min))
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            x,
+            y,
+            colormap: control_points(colormap),
+            limits,
+            title,
+            columns,
+            rows,
+        }
+    }
+
+    /// draw a `(nx, ny)` field, with `annotation` (such as the time) above the plot
+    pub(crate) fn render(&self, field: ArrayView2<'_, f64>, annotation: &str) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height, WHITE);
+
+        let plot_width = self.columns.len();
+        let plot_height = self.rows.len();
+        let (nx, ny) = field.dim();
+        let (low, high) = self.limits;
+
+        for (row, &(j, wy)) in self.rows.iter().enumerate() {
+            let j_next = (j + 1).min(ny - 1);
+
+            for (col, &(i, wx)) in self.columns.iter().enumerate() {
+                let i_next = (i + 1).min(nx - 1);
+
+                let value = (1. - wx) * (1. - wy) * field[(i, j)]
+                    + wx * (1. - wy) * field[(i_next, j)]
+                    + (1. - wx) * wy * field[(i, j_next)]
+                    + wx * wy * field[(i_next, j_next)];
+
+                let color = sample_colormap(self.colormap, (value - low) / (high - low));
+                canvas.set(MARGIN_LEFT + col, MARGIN_TOP + row, color);
+            }
+        }
+
+        canvas.outline(
+            MARGIN_LEFT - 1,
+            MARGIN_TOP - 1,
+            plot_width + 2,
+            plot_height + 2,
+            BLACK,
+        );
+
+        self.draw_x_axis(&mut canvas);
+        self.draw_y_axis(&mut canvas);
+        self.draw_colorbar(&mut canvas);
+
+        canvas.text(
+            MARGIN_LEFT,
+            (MARGIN_TOP - CHAR_HEIGHT) / 2,
+            &self.title,
+            BLACK,
+        );
+        canvas.text(
+            (MARGIN_LEFT + plot_width).saturating_sub(text_width(annotation)),
+            (MARGIN_TOP - CHAR_HEIGHT) / 2,
+            annotation,
+            BLACK,
+        );
+
+        canvas
+    }
+
+    fn draw_x_axis(&self, canvas: &mut Canvas) {
+        let plot_width = self.columns.len();
+        let bottom = MARGIN_TOP + self.rows.len();
+        let (x_min, x_max) = (self.x[0], self.x[self.x.len() - 1]);
+
+        let (ticks, decimals) = nice_ticks(x_min, x_max, 8);
+
+        for tick in ticks {
+            let fraction = (tick - x_min) / (x_max - x_min);
+            let col = MARGIN_LEFT + (fraction * (plot_width - 1) as f64).round() as usize;
+            canvas.fill_rect(col, bottom, 1, TICK_LENGTH, BLACK);
+
+            let label = format!("{tick:.decimals$}");
+            canvas.text(
+                col.saturating_sub(text_width(&label) / 2),
+                bottom + TICK_LENGTH + 4,
+                &label,
+                BLACK,
+            );
+        }
+
+        canvas.text(
+            MARGIN_LEFT + plot_width / 2,
+            bottom + TICK_LENGTH + CHAR_HEIGHT + 12,
+            "x",
+            BLACK,
+        );
+    }
+
+    fn draw_y_axis(&self, canvas: &mut Canvas) {
+        let plot_height = self.rows.len();
+        let (y_min, y_max) = (self.y[0], self.y[self.y.len() - 1]);
+
+        let (ticks, decimals) = nice_ticks(y_min, y_max, 5);
+
+        for tick in ticks {
+            let fraction = (y_max - tick) / (y_max - y_min);
+            let row = MARGIN_TOP + (fraction * (plot_height - 1) as f64).round() as usize;
+            canvas.fill_rect(MARGIN_LEFT - 1 - TICK_LENGTH, row, TICK_LENGTH, 1, BLACK);
+
+            let label = format!("{tick:.decimals$}");
+            canvas.text(
+                (MARGIN_LEFT - TICK_LENGTH - 6).saturating_sub(text_width(&label)),
+                row.saturating_sub(CHAR_HEIGHT / 2),
+                &label,
+                BLACK,
+            );
+        }
+
+        canvas.text(8, MARGIN_TOP + plot_height / 2, "y", BLACK);
+    }
+
+    fn draw_colorbar(&self, canvas: &mut Canvas) {
+        let plot_height = self.rows.len();
+        let left = self.width - MARGIN_RIGHT + 24;
+        let (low, high) = self.limits;
+
+        for row in 0..plot_height {
+            let fraction = 1. - (row as f64 + 0.5) / plot_height as f64;
+            let color = sample_colormap(self.colormap, fraction);
+            canvas.fill_rect(left, MARGIN_TOP + row, COLORBAR_WIDTH, 1, color);
+        }
+
+        canvas.outline(
+            left - 1,
+            MARGIN_TOP - 1,
+            COLORBAR_WIDTH + 2,
+            plot_height + 2,
+            BLACK,
+        );
+
+        let (ticks, decimals) = nice_ticks(low, high, 5);
+
+        for tick in ticks {
+            let fraction = (high - tick) / (high - low);
+            let row = MARGIN_TOP + (fraction * (plot_height - 1) as f64).round() as usize;
+            canvas.fill_rect(left + COLORBAR_WIDTH + 1, row, TICK_LENGTH / 2, 1, BLACK);
+
+            canvas.text(
+                left + COLORBAR_WIDTH + TICK_LENGTH + 2,
+                row.saturating_sub(CHAR_HEIGHT / 2),
+                &format!("{tick:.decimals$}"),
+                BLACK,
+            );
+        }
+    }
+}
+
+/// rows of a 5x7 glyph, with the most significant of the lower 5 bits as the left column.
+/// Upper case letters are drawn as lower case, and unknown characters as `?`
+fn glyph(character: char) -> [u8; 7] {
+    match character.to_ascii_lowercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'a' => [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F],
+        'b' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E],
+        'c' => [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E],
+        'd' => [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        'f' => [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08],
+        'g' => [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'h' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'i' => [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E],
+        'j' => [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C],
+        'k' => [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+        'l' => [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'm' => [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11],
+        'n' => [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'o' => [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E],
+        'p' => [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10],
+        'q' => [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01],
+        'r' => [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],
+        's' => [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E],
+        't' => [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06],
+        'u' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D],
+        'v' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'w' => [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A],
+        'x' => [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11],
+        'y' => [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'z' => [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+#[test]
+fn ticks_are_round_numbers() {
+    let (ticks, decimals) = nice_ticks(0.03, 0.97, 5);
+    assert_eq!(decimals, 1);
+    assert_eq!(ticks.len(), 4);
+    assert!((ticks[0] - 0.2).abs() < 1e-12);
+    assert!((ticks[3] - 0.8).abs() < 1e-12);
+
+    let (ticks, decimals) = nice_ticks(-10., 30., 4);
+    assert_eq!(decimals, 0);
+    assert_eq!(ticks, vec![-10., 0., 10., 20., 30.]);
+}
+
+#[test]
+fn non_uniform_interpolation() {
+    let grid = [0., 1., 3., 7.];
+
+    assert_eq!(interpolation_weights(&grid, 2.), (1, 0.5));
+    assert_eq!(interpolation_weights(&grid, 7.), (2, 1.));
+    // points outside of the grid take the value at its edge
+    assert_eq!(interpolation_weights(&grid, -1.), (0, 0.));
+
+    let points = control_points(cli::Colormap::Gray);
+    assert_eq!(sample_colormap(points, 0.5), [128, 128, 128]);
+    assert_eq!(sample_colormap(points, 2.), [255, 255, 255]);
+}
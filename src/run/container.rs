@@ -4,7 +4,7 @@ use super::create_dirs;
 use super::postprocess;
 
 /// running routine for the solver once activated within the container
-pub(crate) fn run_container(_args: cli::RunContainer) -> anyhow::Result<()> {
+pub(crate) fn run_container(args: cli::RunContainer) -> anyhow::Result<()> {
     let start = std::time::Instant::now();
 
     let path = PathBuf::from("/input/input.json");
@@ -14,15 +14,13 @@ pub(crate) fn run_container(_args: cli::RunContainer) -> anyhow::Result<()> {
     // initialize some base directories within the folder we will work in
     create_dirs(&dist_save)?;
 
-    // copy input.json to the output
-    fs::copy(&path, "/distribute_save/input.json").unwrap();
-    fs::copy("/input/database_bl.dat", "/distribute_save/database_bl.dat").unwrap();
-
-    // read in the config json file
-    let file = fs::File::open(&path)
-        .with_context(|| format!("failed to open input.json file at {}", path.display()))?;
+    // read in the config, merging in any configs that it extends
+    let config = Config::load(&path, &Default::default())
+        .with_context(|| format!("failed to read input.json file at {}", path.display()))?;
 
-    let config: Config = serde_json::from_reader(file)?;
+    // write the resolved config to the output, so that postprocessing does not need its bases
+    config.to_file("/distribute_save/input.json")?;
+    fs::copy("/input/database_bl.dat", "/distribute_save/database_bl.dat").unwrap();
 
     // change the current working directory to the distribute_save directory. That way, all the
     // file that we need to run and work with will be output here
@@ -44,7 +42,7 @@ pub(crate) fn run_container(_args: cli::RunContainer) -> anyhow::Result<()> {
     let sh = xshell::Shell::new()?;
 
     // choose the nproc
-    let nproc = (config.mpi_x_split * 1).to_string();
+    let nproc = config.nproc().to_string();
 
     if config.use_python {
         let runtime_py = PathBuf::from("/runtimesolver/");
@@ -70,7 +68,7 @@ pub(crate) fn run_container(_args: cli::RunContainer) -> anyhow::Result<()> {
         exec.run()?;
     }
 
-    postprocess(&config)?;
+    postprocess(&config, &args)?;
 
     let end = start.elapsed();
     let hours = end.as_secs() / 3600;
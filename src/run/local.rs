@@ -99,7 +99,11 @@ pub(crate) fn run_local(args: cli::RunLocal) -> Result<()> {
 
     let solver = Solver::new(args.workdir)?;
 
-    solver.load_input_file(&args.config, "input.json")?;
+    // the container only reads json configs
+    let config = Config::load(&args.config, &args.overrides)
+        .with_context(|| format!("failed to read config at {}", args.config.display()))?;
+    config.to_file(solver.input.join("input.json"))?;
+
     solver.load_input_file(&args.database, "database_bl.dat")?;
 
     // if a directory was specified to run the solver then we format it to a binding for the
@@ -1,4 +1,6 @@
 use crate::prelude::*;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
 mod container;
 mod local;
 
@@ -49,8 +51,7 @@ impl MeshInfo {
         let yg = base.join("y.dat");
         let zg = base.join("z.dat");
 
-        // TODO: update this in the config generation - but i doubt it will ever change
-        let ghost_nodes = 3;
+        let ghost_nodes = crate::config_generator::GHOST_POINTS;
 
         let x_data = read_mesh_info(&xg, ghost_nodes, config.x_divisions)?;
         let y_data = read_mesh_info(&yg, ghost_nodes, config.y_divisions)?;
@@ -62,48 +63,111 @@ impl MeshInfo {
             z_data,
         })
     }
+
+    /// the coordinates along each axis, named by their axis
+    pub(crate) fn arrays(&self) -> [(&'static str, ndarray::ArrayView1<'_, f64>); 3] {
+        [
+            ("x", ndarray::ArrayView1::from(self.x_data.as_slice())),
+            ("y", ndarray::ArrayView1::from(self.y_data.as_slice())),
+            ("z", ndarray::ArrayView1::from(self.z_data.as_slice())),
+        ]
+    }
 }
 
 /// general parent postprocessing routine to be called after the solver has finished
-fn postprocess(config: &Config) -> Result<()> {
+///
+/// see [`cli::RunContainer`] for the options that control postprocessing
+fn postprocess(config: &Config, args: &cli::RunContainer) -> Result<()> {
     let data_location = PathBuf::from("/distribute_save");
     let mesh_info = MeshInfo::from_base_path(&data_location, config)?;
 
     // convert all the binary spans to vtk files
-    convert_spans(&data_location, config, &mesh_info, true)?;
+    convert_spans(
+        &data_location,
+        config,
+        &mesh_info,
+        true,
+        args.keep_going,
+        args.jobs,
+    )?;
 
     // write the probes to a folder + create matfiles folder
-    write_probes(&data_location)?;
+    write_probes(&data_location, args.keep_going)?;
 
     // write the mesh information to the matfiles folder
-    let mesh_path = data_location.join("matfiles/mesh.mat");
-    let writer = fs::File::create(&mesh_path).with_context(|| {
-        format!(
-            "failed to create directory for mesh information: {}",
-            mesh_path.display()
-        )
-    })?;
-    mat5::MatFile::write_contents(&mesh_info, writer)?;
+    let matfiles = data_location.join("matfiles");
+    match args.mesh_format {
+        cli::ArrayFormat::Mat => {
+            let mesh_path = matfiles.join("mesh.mat");
+            let writer = fs::File::create(&mesh_path).with_context(|| {
+                format!(
+                    "failed to create directory for mesh information: {}",
+                    mesh_path.display()
+                )
+            })?;
+            mat5::MatFile::write_contents(&mesh_info, writer)?;
+        }
+        cli::ArrayFormat::Npy => {
+            for (name, data) in mesh_info.arrays() {
+                let path = matfiles.join(format!("mesh_{name}.npy"));
+                crate::npy::write_npy_file(&path, &data)?;
+            }
+        }
+        cli::ArrayFormat::Npz => {
+            let mut npz = crate::npy::NpzWriter::create(&matfiles.join("mesh.npz"))?;
+            for (name, data) in mesh_info.arrays() {
+                npz.add_array(name, &data)?;
+            }
+            npz.finish()?;
+        }
+        cli::ArrayFormat::Mat73 | cli::ArrayFormat::Hdf5 => {
+            let (file_name, matlab) = match args.mesh_format {
+                cli::ArrayFormat::Mat73 => ("mesh.mat", true),
+                _ => ("mesh.h5", false),
+            };
+            let writer = crate::mat73::Hdf5ArrayWriter::create(&matfiles.join(file_name), matlab)?;
+            for (name, data) in mesh_info.arrays() {
+                writer.write_array(name, data)?;
+            }
+            writer.finish()?;
+        }
+    }
 
     Ok(())
 }
 
 /// helper function for assembling all the elements to write all binary data to .mat files
-fn write_probes(location: &Path) -> Result<(), Error> {
+fn write_probes(location: &Path, keep_going: bool) -> Result<(), Error> {
     let probe_folder = location.join("csv_data");
     let output_folder = location.join("matfiles");
     let config = location.join("/input/input.json");
-    let args = cli::ParseProbe::new(probe_folder, output_folder, config);
+    let args = cli::ParseProbe::new(
+        probe_folder,
+        output_folder,
+        config,
+        Some(location.to_owned()),
+        keep_going,
+        cli::ProbeFormat::Mat,
+        cli::ConfigOverrides::default(),
+    );
     crate::probe::probe(args)?;
     Ok(())
 }
 
 /// Convert all .binary files in the ./spans directory to Vtk files using mesh information
+///
+/// If `keep_going` is set, files that fail to convert are left in place and reported once every
+/// other file has been converted, instead of stopping at the first error
+///
+/// Files are converted in parallel on at most `jobs` threads (every core if `None`). Each
+/// `.binary` file is written to a `.vtr` file with the same name next to it.
 pub(crate) fn convert_spans(
     data_location: &Path,
     config: &Config,
     mesh_info: &MeshInfo,
     remove_binary: bool,
+    keep_going: bool,
+    jobs: Option<usize>,
 ) -> Result<(), Error> {
     let spans_folder = data_location.join("spans");
 
@@ -114,7 +178,7 @@ pub(crate) fn convert_spans(
     let spans = vtk::Spans2D::new(config.x_divisions, config.y_divisions);
     let domain = vtk::Rectilinear2D::new(mesh, spans);
 
-    for file in walkdir::WalkDir::new(&spans_folder)
+    let mut files = walkdir::WalkDir::new(&spans_folder)
         .into_iter()
         .filter_map(|e| e.ok())
         // the first item will be the root folder we created
@@ -123,44 +187,102 @@ pub(crate) fn convert_spans(
         .filter(|e| {
             e.path()
                 .extension()
-                .map(|ext| ext != "vtr")
+                .map(|ext| ext == "binary")
                 .unwrap_or(false)
         })
-    {
-        let path = file.path();
+        .map(|e| e.into_path())
+        .collect::<Vec<_>>();
 
-        let file_name = path.file_stem().unwrap().to_string_lossy();
-        let output_name = format!("{}.vtr", file_name);
-        let output_path = spans_folder.join(output_name);
+    // sorted so that the conversion order and any reported errors are the same between runs
+    files.sort_unstable();
 
-        // read the data to something we can write a vtk with
+    let total = files.len();
+    let completed = AtomicUsize::new(0);
+    // report roughly every 5% of the files
+    let report_every = (total / 20).max(1);
 
-        let mut file = fs::File::open(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+    println!("converting {total} span files to vtk");
 
-        // five arrays, each taking nx * ny points, and each point uses 8 bytes
-        let mut buffer = Vec::with_capacity(8 * config.x_divisions * config.y_divisions * 5);
-        file.read_to_end(&mut buffer).unwrap();
-        let float_bytes = utils::bytes_to_float(&buffer);
+    // each file is independent, so they are converted in parallel
+    let convert = |path: &PathBuf| {
+        let result = convert_span(path, config, domain.clone(), remove_binary);
 
-        let data = binary_to_vtk::convert_binary_to_vtk_information(&float_bytes, config)?;
+        let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if done % report_every == 0 || done == total {
+            println!("converted {done}/{total} span files");
+        }
 
-        let vtk = vtk::VtkData::new(domain.clone(), data);
+        result
+    };
 
-        let writer = io::BufWriter::new(
-            fs::File::create(&output_path)
-                .map_err(|e| FileError::new(output_path.to_owned(), e))?,
-        );
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
 
-        vtk::write_vtk(writer, vtk)?;
+    if !keep_going {
+        pool.install(|| files.par_iter().try_for_each(convert))?;
+        return Ok(());
+    }
 
-        if remove_binary {
-            fs::remove_file(path).unwrap()
+    // collecting a parallel iterator preserves the order of `files`
+    let skipped = pool.install(|| {
+        files
+            .par_iter()
+            .filter_map(|path| convert(path).err().map(|e| (path, e)))
+            .collect::<Vec<_>>()
+    });
+
+    if !skipped.is_empty() {
+        println!("{} span files could not be converted:", skipped.len());
+        for (path, e) in skipped {
+            println!("\t{}: {e}", path.display());
         }
     }
 
     Ok(())
 }
 
+/// convert a single span .binary file to a .vtr file with the same name
+fn convert_span(
+    path: &Path,
+    config: &Config,
+    domain: vtk::Rectilinear2D<f64, vtk::Ascii>,
+    remove_binary: bool,
+) -> Result<(), Error> {
+    let output_path = path.with_extension("vtr");
+    // written to a temporary file first so that an interrupted conversion never leaves a
+    // truncated .vtr file behind
+    let partial_path = path.with_extension("vtr.partial");
+
+    // read the data to something we can write a vtk with
+    let data = binary_to_vtk::read_span_binary(path, config)?;
+
+    let vtk = vtk::VtkData::new(domain, data);
+
+    let writer = io::BufWriter::new(
+        fs::File::create(&partial_path).map_err(|e| FileError::new(partial_path.to_owned(), e))?,
+    );
+
+    let written = vtk::write_vtk(writer, vtk)
+        .map_err(Error::from)
+        .and_then(|_| {
+            fs::rename(&partial_path, &output_path)
+                .map_err(|e| FileError::new(output_path.to_owned(), e).into())
+        });
+
+    if let Err(e) = written {
+        // the partial file is incomplete, so it is removed (if it can be) along with the error
+        fs::remove_file(&partial_path).ok();
+        return Err(e);
+    }
+
+    if remove_binary {
+        fs::remove_file(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+    }
+
+    Ok(())
+}
+
 #[test]
 fn read_mesh_file() {
     let file = PathBuf::from("./static/x.dat");
@@ -0,0 +1,268 @@
+//! helpers for loading span average snapshots from the .vtr files in a solver's `spans` folder
+
+use crate::binary_to_vtk::SpanVtkInformation;
+use crate::prelude::*;
+use ndarray::s;
+use vtk::{Scalar2D, Vector2D};
+
+/// the number of fields stored for every snapshot: rho, u, v, w
+pub(crate) const SNAPSHOT_FIELDS: usize = 4;
+
+/// name of the file that `spans-to-hdf5` packs the span averages of a run into
+pub(crate) const SPAN_AVERAGES_FILE: &str = "span_averages.h5";
+
+/// datasets of a span averages hdf5 file that hold each field, every one of which is shaped
+/// `(num_snapshots, nx, ny)`. The first `SNAPSHOT_FIELDS` are the snapshot fields.
+pub(crate) const SPAN_HDF5_FIELDS: [&str; 5] = ["rho", "u", "v", "w", "energy"];
+
+#[derive(vtk::DataArray, Clone)]
+/// rho and velocity components of a single snapshot shaped field, such as a
+/// decomposition mode or the mean flow
+struct SnapshotVtkInformation {
+    rho: Scalar2D<f64>,
+    velocity: Vector2D<f64>,
+}
+
+type SpanVtk = vtk::VtkData<vtk::Rectilinear2D<f64, vtk::Binary>, SpanVtkInformation>;
+
+/// read the data of a single span average .vtr file
+pub(crate) fn read_span_vtk(path: &Path) -> Result<SpanVtkInformation, Error> {
+    let vtk_data: SpanVtk = vtk::read_vtk(path)?;
+    Ok(vtk_data.data)
+}
+
+/// find every span average .vtr file in a folder, sorted in the order they were written
+pub(crate) fn span_vtk_files(spans_folder: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = fs::read_dir(spans_folder)
+        .map_err(|e| FileError::new(spans_folder.to_owned(), e))?
+        .filter_map(|entry_res| entry_res.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|ext| ext == "vtr").unwrap_or(false))
+        .collect::<Vec<_>>();
+
+    // file names are zero padded so sorting them lexically sorts them by step
+    files.sort_unstable();
+
+    Ok(files)
+}
+
+/// parse the solver step that a span average was written at from its file name
+///
+/// the fortran code writes span averages as `span_average_[5 char step number]_average.binary`,
+/// which is carried over to the .vtr file name
+pub(crate) fn step_number(path: &Path) -> Option<usize> {
+    let stem = path.file_stem()?.to_string_lossy();
+
+    stem.split('_')
+        .find(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|part| part.parse().ok())
+}
+
+/// copy rho, u, v, w of the span average read from `path` into a `(4, nx, ny)` array
+pub(crate) fn assign_snapshot(
+    information: SpanVtkInformation,
+    mut out: ndarray::ArrayViewMut3<f64>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let rho: Array2 = information.rho.into();
+    let velocity: Array3 = information.velocity.into();
+
+    let (_, nx, ny) = out.dim();
+
+    if rho.dim() != (nx, ny) || velocity.dim() != (3, nx, ny) {
+        anyhow::bail!(
+            "{} is a {} x {} grid, but the config has {nx} x {ny} points",
+            path.display(),
+            rho.dim().0,
+            rho.dim().1,
+        );
+    }
+
+    out.slice_mut(s![0usize, .., ..]).assign(&rho);
+    out.slice_mut(s![1usize..4, .., ..]).assign(&velocity);
+
+    Ok(())
+}
+
+/// the 2D rectilinear domain of the span averages, used when writing snapshot shaped data to vtk
+pub(crate) fn span_domain(
+    mesh: &run::MeshInfo,
+    config: &Config,
+) -> vtk::Rectilinear2D<f64, vtk::Ascii> {
+    // currently not possible to write arrays in binary for 2D files
+    let mesh = vtk::Mesh2D::<_, vtk::Ascii>::new(mesh.x_data.clone(), mesh.y_data.clone());
+    let spans = vtk::Spans2D::new(config.x_divisions, config.y_divisions);
+    vtk::Rectilinear2D::new(mesh, spans)
+}
+
+/// write a `(4, nx, ny)` array of rho, u, v, w to a .vtr file
+pub(crate) fn write_snapshot_vtk(
+    path: &Path,
+    domain: vtk::Rectilinear2D<f64, vtk::Ascii>,
+    field: Array3,
+) -> anyhow::Result<()> {
+    let data = SnapshotVtkInformation {
+        rho: Scalar2D::new(field.slice(s![0usize, .., ..]).to_owned()),
+        velocity: Vector2D::new(field.slice(s![1usize..4, .., ..]).to_owned()),
+    };
+
+    let writer = io::BufWriter::new(
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?,
+    );
+    vtk::write_vtk(writer, vtk::VtkData::new(domain, data))?;
+
+    Ok(())
+}
+
+/// every span average of a run loaded into memory
+pub(crate) struct SpanSnapshots {
+    /// solver step of each snapshot
+    pub(crate) steps: Vec<usize>,
+    /// `(num_snapshots, 4, nx, ny)` array of rho, u, v, w
+    pub(crate) data: Array4,
+}
+
+impl SpanSnapshots {
+    /// load each of the files (which should already be sorted) into a single array
+    pub(crate) fn load(files: &[PathBuf], config: &Config) -> anyhow::Result<Self> {
+        let mut data = Array4::zeros((
+            files.len(),
+            SNAPSHOT_FIELDS,
+            config.x_divisions,
+            config.y_divisions,
+        ));
+        let mut steps = Vec::with_capacity(files.len());
+
+        for (idx, file) in files.iter().enumerate() {
+            println!("reading {}", file.display());
+
+            let information = read_span_vtk(file)?;
+            assign_snapshot(information, data.slice_mut(s![idx, .., .., ..]), file)?;
+
+            steps.push(step_number(file).unwrap_or(idx));
+        }
+
+        Ok(Self { steps, data })
+    }
+
+    /// load the snapshots from a span averages hdf5 file written by `spans-to-hdf5`, which must
+    /// be on the grid of the config
+    pub(crate) fn load_hdf5(path: &Path, config: &Config) -> anyhow::Result<Self> {
+        let file = hdf5::File::open(path)
+            .with_context(|| format!("failed to open span averages file {}", path.display()))?;
+
+        let dataset = |name: &str| {
+            file.dataset(name).with_context(|| {
+                format!(
+                    "dataset `{name}` was missing from h5 file {}",
+                    path.display()
+                )
+            })
+        };
+
+        let steps = dataset("step")?
+            .read_1d::<u64>()
+            .with_context(|| format!("failed to read steps from {}", path.display()))?
+            .iter()
+            .map(|step| *step as usize)
+            .collect::<Vec<_>>();
+
+        let mut data: Option<Array4> = None;
+
+        for (idx, name) in SPAN_HDF5_FIELDS.iter().take(SNAPSHOT_FIELDS).enumerate() {
+            let field: Array3 = dataset(name)?
+                .read()
+                .with_context(|| format!("failed to read `{name}` from {}", path.display()))?;
+
+            let (num_snapshots, nx, ny) = field.dim();
+
+            if (nx, ny) != (config.x_divisions, config.y_divisions) {
+                anyhow::bail!(
+                    "dataset `{name}` in {} is on a {nx} x {ny} grid, but the config has {} x {} points",
+                    path.display(),
+                    config.x_divisions,
+                    config.y_divisions
+                );
+            }
+            let data =
+                data.get_or_insert_with(|| Array4::zeros((num_snapshots, SNAPSHOT_FIELDS, nx, ny)));
+
+            if data.dim() != (num_snapshots, SNAPSHOT_FIELDS, nx, ny) {
+                anyhow::bail!(
+                    "dataset `{name}` in {} has shape {:?}, which does not match the other fields",
+                    path.display(),
+                    field.dim()
+                );
+            }
+
+            data.slice_mut(s![.., idx, .., ..]).assign(&field);
+        }
+
+        let data = data.expect("there is at least one field");
+
+        if steps.len() != data.dim().0 {
+            anyhow::bail!(
+                "{} has {} steps but {} snapshots",
+                path.display(),
+                steps.len(),
+                data.dim().0
+            );
+        }
+
+        Ok(Self { steps, data })
+    }
+
+    /// load every span average of a run, from `span_averages.h5` if `spans-to-hdf5` has been run
+    /// and otherwise from the .vtr files in the `spans` folder
+    pub(crate) fn from_solver_results(
+        solver_results: &Path,
+        config: &Config,
+    ) -> anyhow::Result<Self> {
+        let hdf5_path = solver_results.join(SPAN_AVERAGES_FILE);
+
+        if hdf5_path.exists() {
+            println!("reading span averages from {}", hdf5_path.display());
+            return Self::load_hdf5(&hdf5_path, config);
+        }
+
+        let files = span_vtk_files(&solver_results.join("spans"))?;
+        Self::load(&files, config)
+    }
+
+    pub(crate) fn num_snapshots(&self) -> usize {
+        self.data.dim().0
+    }
+
+    /// view the snapshots as a `(num_snapshots, 4 * nx * ny)` matrix with one snapshot per row
+    pub(crate) fn matrix(&self) -> ndarray::ArrayView2<'_, f64> {
+        let (num_snapshots, fields, nx, ny) = self.data.dim();
+        self.data
+            .view()
+            .into_shape((num_snapshots, fields * nx * ny))
+            .expect("snapshot data is allocated in standard layout")
+    }
+}
+
+#[test]
+fn parse_span_step_number() {
+    let path = PathBuf::from("./distribute_save/spans/span_average_00010_average.vtr");
+    assert_eq!(step_number(&path), Some(10));
+}
+
+#[test]
+fn snapshot_on_another_grid_is_an_error() {
+    let information = |nx: usize, ny: usize| SpanVtkInformation {
+        rho: Scalar2D::new(Array2::zeros((nx, ny))),
+        velocity: Vector2D::new(Array3::zeros((3, nx, ny))),
+        energy: Scalar2D::new(Array2::zeros((nx, ny))),
+    };
+    let path = Path::new("span_average_00010_average.vtr");
+    let mut out = Array3::zeros((SNAPSHOT_FIELDS, 4, 3));
+
+    assert!(assign_snapshot(information(4, 3), out.view_mut(), path).is_ok());
+
+    let error = assign_snapshot(information(5, 3), out.view_mut(), path)
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("span_average_00010"));
+}
@@ -0,0 +1,310 @@
+//! pack the span averages of a run into a single chunked, compressed hdf5 file
+//!
+//! the file contains
+//!
+//! | Dataset  | Shape              | Data |
+//! |----------|--------------------|------|
+//! | rho      | `(n, nx, ny)`      | density |
+//! | u, v, w  | `(n, nx, ny)`      | velocity components |
+//! | energy   | `(n, nx, ny)`      | energy |
+//! | step     | `(n)`              | solver step of each span average |
+//! | time     | `(n)`              | physical time of each span average (NaN if the timestep is unknown) |
+//! | x, y     | `(nx)`, `(ny)`     | mesh coordinates |
+//!
+//! along with a `config` attribute holding the input.json of the run. Each field is chunked by
+//! span average so that single snapshots can be read cheaply.
+
+use crate::binary_to_vtk::SpanVtkInformation;
+use crate::prelude::*;
+use crate::snapshots;
+use crate::snapshots::SPAN_HDF5_FIELDS;
+
+use hdf5::types::VarLenUnicode;
+use ndarray::s;
+use rayon::prelude::*;
+
+pub(crate) fn spans_to_hdf5(args: cli::SpansToHdf5) -> anyhow::Result<()> {
+    let config_path = args.solver_results.join("input.json");
+    let config = Config::load(&config_path, &args.overrides)
+        .with_context(|| format!("failed to read config at path {}", config_path.display()))?;
+
+    let mesh = run::MeshInfo::from_base_path(&args.solver_results, &config)?;
+
+    let spans_folder = args.solver_results.join("spans");
+    let files = span_files(&spans_folder)?;
+
+    if files.is_empty() {
+        anyhow::bail!(
+            "no span average .binary or .vtr files found in {}",
+            spans_folder.display()
+        );
+    }
+
+    let dt = match config.timestep(args.dt) {
+        Ok(dt) => dt,
+        Err(e) => {
+            println!("{e}. Span average times will be written as NaN");
+            f64::NAN
+        }
+    };
+
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.solver_results.join(snapshots::SPAN_AVERAGES_FILE));
+
+    let writer = SpanHdf5Writer::create(&output, files.len(), &config, &mesh)?;
+
+    let mut steps = Vec::with_capacity(files.len());
+
+    for (batch_idx, batch) in files.chunks(utils::BATCH_SIZE).enumerate() {
+        let spans = batch
+            .par_iter()
+            .map(|path| read_span(path, &config))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (offset, span) in spans.into_iter().enumerate() {
+            writer.write_span(batch_idx * utils::BATCH_SIZE + offset, span)?;
+        }
+
+        steps.extend(
+            batch
+                .iter()
+                .enumerate()
+                .map(|(offset, path)| {
+                    snapshots::step_number(path).unwrap_or(batch_idx * utils::BATCH_SIZE + offset)
+                })
+                .map(|step| step as u64),
+        );
+
+        println!(
+            "wrote {}/{} span averages",
+            (batch_idx * utils::BATCH_SIZE + batch.len()),
+            files.len()
+        );
+    }
+
+    writer.write_steps(&steps, dt)?;
+
+    println!("span averages written to {}", output.display());
+
+    Ok(())
+}
+
+/// the span average files of a run, sorted by step. The .binary files written by the solver are
+/// preferred, and the .vtr files are used if the binaries have already been removed
+fn span_files(spans_folder: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let files = fs::read_dir(spans_folder)
+        .with_context(|| format!("failed to read spans folder {}", spans_folder.display()))?
+        .filter_map(|entry_res| entry_res.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+
+    let with_extension = |extension: &str| {
+        let mut matching = files
+            .iter()
+            .filter(|path| {
+                path.extension()
+                    .map(|ext| ext == extension)
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        // file names are zero padded so sorting them lexically sorts them by step
+        matching.sort_unstable();
+        matching
+    };
+
+    let binaries = with_extension("binary");
+
+    if binaries.is_empty() {
+        Ok(with_extension("vtr"))
+    } else {
+        Ok(binaries)
+    }
+}
+
+fn read_span(path: &Path, config: &Config) -> anyhow::Result<SpanVtkInformation> {
+    let span = if path.extension().map(|ext| ext == "vtr").unwrap_or(false) {
+        snapshots::read_span_vtk(path)
+    } else {
+        binary_to_vtk::read_span_binary(path, config)
+    };
+
+    span.with_context(|| format!("failed to read span average {}", path.display()))
+}
+
+/// handles to every dataset of a span averages file being written
+struct SpanHdf5Writer {
+    file: hdf5::File,
+    fields: Vec<hdf5::Dataset>,
+}
+
+impl SpanHdf5Writer {
+    /// create the file with space for `num_spans` span averages, writing the mesh and config
+    fn create(
+        path: &Path,
+        num_spans: usize,
+        config: &Config,
+        mesh: &run::MeshInfo,
+    ) -> anyhow::Result<Self> {
+        let nx = config.x_divisions;
+        let ny = config.y_divisions;
+
+        let file = hdf5::File::create(path)
+            .with_context(|| format!("failed to create hdf5 file {}", path.display()))?;
+
+        let fields = SPAN_HDF5_FIELDS
+            .iter()
+            .map(|name| {
+                file.new_dataset::<f64>()
+                    .shape((num_spans, nx, ny))
+                    .chunk((1, nx, ny))
+                    .deflate(utils::COMPRESSION_LEVEL)
+                    .create(*name)
+                    .with_context(|| format!("failed to create dataset `{name}`"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let x = ndarray::Array1::from(mesh.x_data.clone());
+        let y = ndarray::Array1::from(mesh.y_data.clone());
+
+        file.new_dataset_builder()
+            .with_data(&x)
+            .create("x")
+            .with_context(|| "failed to write x coordinates")?;
+        file.new_dataset_builder()
+            .with_data(&y)
+            .create("y")
+            .with_context(|| "failed to write y coordinates")?;
+
+        let config_json: VarLenUnicode = serde_json::to_string(config)?.parse()?;
+        file.new_attr::<VarLenUnicode>()
+            .shape(())
+            .create("config")
+            .with_context(|| "failed to create config attribute")?
+            .write_scalar(&config_json)
+            .with_context(|| "failed to write config attribute")?;
+
+        Ok(Self { file, fields })
+    }
+
+    /// write the fields of a single span average at index `idx`
+    fn write_span(&self, idx: usize, span: SpanVtkInformation) -> anyhow::Result<()> {
+        let rho: Array2 = span.rho.into();
+        let velocity: Array3 = span.velocity.into();
+        let energy: Array2 = span.energy.into();
+
+        let fields = [
+            rho.view(),
+            velocity.slice(s![0usize, .., ..]),
+            velocity.slice(s![1usize, .., ..]),
+            velocity.slice(s![2usize, .., ..]),
+            energy.view(),
+        ];
+
+        for (dataset, (field, name)) in self
+            .fields
+            .iter()
+            .zip(fields.into_iter().zip(SPAN_HDF5_FIELDS))
+        {
+            dataset
+                .write_slice(field, s![idx, .., ..])
+                .with_context(|| format!("failed to write `{name}` of span average {idx}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// write the step and time of every span average
+    fn write_steps(&self, steps: &[u64], dt: f64) -> anyhow::Result<()> {
+        let time = steps
+            .iter()
+            .map(|step| *step as f64 * dt)
+            .collect::<ndarray::Array1<f64>>();
+        let steps = ndarray::Array1::from(steps.to_vec());
+
+        self.file
+            .new_dataset_builder()
+            .with_data(&steps)
+            .create("step")
+            .with_context(|| "failed to write span average steps")?;
+        self.file
+            .new_dataset_builder()
+            .with_data(&time)
+            .create("time")
+            .with_context(|| "failed to write span average times")?;
+
+        Ok(())
+    }
+}
+
+#[test]
+/// span binaries packed into a file read back as the same snapshots, with their steps and mesh
+fn packed_spans_load_as_snapshots() {
+    let dir = std::env::temp_dir().join(format!("streams_utils_spans_{}", std::process::id()));
+    fs::create_dir_all(dir.join("spans")).unwrap();
+
+    let mut config = cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable();
+    config.x_divisions = 4;
+    config.y_divisions = 3;
+    config.z_divisions = 2;
+    config.mpi_x_split = 2;
+    config.fixed_dt = Some(0.5);
+    config.to_file(dir.join("input.json")).unwrap();
+
+    // three ghost points come before the coordinates
+    for (name, divisions) in [("x.dat", 4), ("y.dat", 3), ("z.dat", 2)] {
+        let coordinates = (0..divisions + 3)
+            .map(|idx| format!("{}\n", idx as f64 - 3.))
+            .collect::<String>();
+        fs::write(dir.join(name), coordinates).unwrap();
+    }
+
+    let value = |step: usize, field: usize, i: usize, j: usize| {
+        step as f64 + field as f64 / 10. + i as f64 * 100. + j as f64 * 1000.
+    };
+
+    for step in [10, 20] {
+        // processes along x hold consecutive x indices, so the points are written in order
+        let bytes = (0..4)
+            .flat_map(|i| (0..3).flat_map(move |j| (0..5).map(move |field| (i, j, field))))
+            .flat_map(|(i, j, field)| value(step, field, i, j).to_le_bytes())
+            .collect::<Vec<u8>>();
+
+        let name = format!("span_average_{step:05}_average.binary");
+        fs::write(dir.join("spans").join(name), bytes).unwrap();
+    }
+
+    spans_to_hdf5(cli::SpansToHdf5 {
+        solver_results: dir.clone(),
+        output: None,
+        dt: None,
+        overrides: Default::default(),
+    })
+    .unwrap();
+
+    let path = dir.join(snapshots::SPAN_AVERAGES_FILE);
+    let spans = snapshots::SpanSnapshots::load_hdf5(&path, &config).unwrap();
+    assert_eq!(spans.steps, [10, 20]);
+    assert_eq!(spans.data.dim(), (2, snapshots::SNAPSHOT_FIELDS, 4, 3));
+    assert_eq!(spans.data[(1, 2, 3, 1)], value(20, 2, 3, 1));
+
+    let file = hdf5::File::open(&path).unwrap();
+
+    for (field, name) in SPAN_HDF5_FIELDS.iter().enumerate() {
+        let data: Array3 = file.dataset(name).unwrap().read().unwrap();
+        assert_eq!(data.dim(), (2, 4, 3), "{name}");
+        assert_eq!(data[(0, 1, 2)], value(10, field, 1, 2), "{name}");
+    }
+
+    let time = file.dataset("time").unwrap().read_raw::<f64>().unwrap();
+    assert_eq!(time, [5., 10.]);
+
+    let x = file.dataset("x").unwrap().read_raw::<f64>().unwrap();
+    let y = file.dataset("y").unwrap().read_raw::<f64>().unwrap();
+    assert_eq!(x, [0., 1., 2., 3.]);
+    assert_eq!(y, [0., 1., 2.]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -4,13 +4,20 @@ use anyhow::Result;
 /// convert a general solver folder full of span binaries to vtk files
 pub(crate) fn spans_to_vtk(args: cli::SpansToVtk) -> Result<()> {
     // load the config file
-    let config = Config::from_path(&args.solver_results.join("input.json"))?;
+    let config = Config::load(&args.solver_results.join("input.json"), &args.overrides)?;
 
     // load the mesh information
     let mesh = run::MeshInfo::from_base_path(&args.solver_results, &config)?;
 
     // then convert all the binar files to vtk files
-    run::convert_spans(&args.solver_results, &config, &mesh, args.clean_binary)?;
+    run::convert_spans(
+        &args.solver_results,
+        &config,
+        &mesh,
+        args.clean_binary,
+        args.keep_going,
+        args.jobs,
+    )?;
 
     Ok(())
 }
@@ -1,20 +1,110 @@
+use crate::prelude::*;
+use std::borrow::Cow;
+
+/// number of files (spans, snapshots or frames) read in parallel before they are written out
+pub(crate) const BATCH_SIZE: usize = 64;
+
+/// gzip level of every compressed array written to HDF5 or netCDF
+pub(crate) const COMPRESSION_LEVEL: u8 = 4;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{length} bytes is not a whole number of 8 byte floats ({remainder} trailing bytes)")]
+/// a buffer of bytes that ended part way through a float
+pub(crate) struct TruncatedBytes {
+    pub(crate) length: usize,
+    pub(crate) remainder: usize,
+}
+
 /// helper function to convert an array of LE bytes to `f64`
-pub(crate) fn bytes_to_float(bytes: &[u8]) -> Vec<f64> {
-    bytes
-        .chunks(8)
-        .into_iter()
+pub(crate) fn bytes_to_float(bytes: &[u8]) -> Result<Vec<f64>, TruncatedBytes> {
+    let chunks = bytes.chunks_exact(8);
+
+    if !chunks.remainder().is_empty() {
+        return Err(TruncatedBytes {
+            length: bytes.len(),
+            remainder: chunks.remainder().len(),
+        });
+    }
+
+    Ok(chunks
         .map(|x| {
             let mut arr = [0; 8];
+            arr.copy_from_slice(x);
+            f64::from_le_bytes(arr)
+        })
+        .collect())
+}
+
+/// a binary file of little endian `f64`s written by the solver, mapped into memory
+pub(crate) struct FloatFile {
+    map: memmap2::Mmap,
+}
+
+impl FloatFile {
+    /// memory map a file. The file must not be modified while it is mapped, which holds for
+    /// solver output that is only read once the solver has finished writing it
+    pub(crate) fn open(path: &Path) -> Result<Self, FileError> {
+        let file = fs::File::open(path).map_err(|e| FileError::new(path.to_owned(), e))?;
+
+        // SAFETY: see the requirements in the doc comment, nothing in this program writes to
+        // files while they are mapped
+        let map =
+            unsafe { memmap2::Mmap::map(&file) }.map_err(|e| FileError::new(path.to_owned(), e))?;
 
-            if x.len() != 8 {
-                panic!("missing information");
+        Ok(Self { map })
+    }
+
+    /// view the contents of the file as floats
+    ///
+    /// on little endian machines the mapped bytes are reinterpreted in place (mappings are page
+    /// aligned). Otherwise the floats are decoded into a new buffer.
+    pub(crate) fn floats(&self) -> Result<Cow<'_, [f64]>, TruncatedBytes> {
+        let bytes: &[u8] = &self.map;
+
+        if bytes.len() % 8 != 0 {
+            return Err(TruncatedBytes {
+                length: bytes.len(),
+                remainder: bytes.len() % 8,
+            });
+        }
+
+        if cfg!(target_endian = "little") {
+            // SAFETY: every bit pattern is a valid f64, and align_to only returns a middle slice
+            // for the part of the buffer that is correctly aligned
+            let (prefix, floats, suffix) = unsafe { bytes.align_to::<f64>() };
+
+            if prefix.is_empty() && suffix.is_empty() {
+                return Ok(Cow::Borrowed(floats));
             }
+        }
 
-            x.into_iter()
-                .enumerate()
-                .for_each(|(idx, val)| arr[idx] = *val);
+        bytes_to_float(bytes).map(Cow::Owned)
+    }
+}
 
-            f64::from_le_bytes(arr)
-        })
-        .collect()
+#[test]
+fn truncated_bytes_are_an_error() {
+    let bytes = [1.5f64.to_le_bytes(), (-2.0f64).to_le_bytes()].concat();
+    assert_eq!(bytes_to_float(&bytes).unwrap(), vec![1.5, -2.0]);
+
+    let err = bytes_to_float(&bytes[..13]).unwrap_err();
+    assert_eq!(err.length, 13);
+    assert_eq!(err.remainder, 5);
+}
+
+#[test]
+fn mapped_floats_match_copied_floats() {
+    let values = [0.5f64, -1.25, 1e300, f64::MIN_POSITIVE];
+    let bytes = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    let path = std::env::temp_dir().join(format!("streams_utils_floats_{}", std::process::id()));
+    fs::write(&path, &bytes).unwrap();
+
+    let file = FloatFile::open(&path).unwrap();
+    assert_eq!(file.floats().unwrap().as_ref(), values.as_slice());
+
+    fs::remove_file(&path).unwrap();
 }
@@ -0,0 +1,563 @@
+//! rules that a config must follow to run in the solver
+//!
+//! every rule has a stable id that is printed with its violations, so that a violation can be
+//! looked up here. Rules with error severity stop the solver from running (or make it crash at
+//! runtime), while rules with warning severity catch parameters that the solver accepts but are
+//! likely a mistake. All rules are checked every time, so every problem with a config is
+//! reported at once.
+//!
+//! rules are added by appending to [`RULES`].
+
+use crate::config_generator::Megabytes;
+use crate::mesh::{ExpectedMesh, MeshError};
+use crate::prelude::*;
+use cli::FlowType;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    #[display(fmt = "error")]
+    Error,
+    #[display(fmt = "warning")]
+    Warning,
+}
+
+/// a single problem found by a rule
+#[derive(Debug, Display, Clone)]
+#[display(fmt = "{} [{}]: {}", severity, rule, message)]
+pub(crate) struct Violation {
+    pub(crate) rule: &'static str,
+    pub(crate) severity: Severity,
+    pub(crate) message: String,
+}
+
+pub(crate) struct Rule {
+    /// stable identifier of the rule
+    pub(crate) id: &'static str,
+    pub(crate) severity: Severity,
+    pub(crate) description: &'static str,
+    /// a message for every violation of the rule. The gpu memory is only known when the config
+    /// is checked against a gpu
+    check: fn(&Config, Option<Megabytes>) -> Vec<String>,
+}
+
+/// every rule that a config is checked against
+pub(crate) const RULES: &[Rule] = &[
+    Rule {
+        id: "mpi-split-positive",
+        severity: Severity::Error,
+        description: "mpi-x-split and mpi-z-split are at least 1",
+        check: mpi_split_positive,
+    },
+    Rule {
+        id: "mpi-split-x",
+        severity: Severity::Error,
+        description: "x-divisions is divisible by mpi-x-split",
+        check: mpi_split_x,
+    },
+    Rule {
+        id: "mpi-split-y",
+        severity: Severity::Error,
+        description: "y-divisions is divisible by mpi-x-split",
+        check: mpi_split_y,
+    },
+    Rule {
+        id: "mpi-split-z",
+        severity: Severity::Error,
+        description: "z-divisions is divisible by mpi-z-split",
+        check: mpi_split_z,
+    },
+    Rule {
+        id: "gpu-memory",
+        severity: Severity::Error,
+        description: "the part of the domain on every mpi rank fits in the memory of its gpu",
+        check: gpu_memory,
+    },
+    Rule {
+        id: "wall-resolved-points",
+        severity: Severity::Error,
+        description: "nymax-wr is less than y-divisions",
+        check: wall_resolved_points,
+    },
+    Rule {
+        id: "wall-resolved-length",
+        severity: Severity::Error,
+        description: "rly-wr is less than y-length",
+        check: wall_resolved_length,
+    },
+    Rule {
+        id: "wall-resolved-stretching",
+        // the previewed grid has not been compared with a grid generated by the solver yet
+        severity: Severity::Warning,
+        description: "the wall resolved region can be stretched to the wall spacing of the solver",
+        check: wall_resolved_stretching,
+    },
+    Rule {
+        id: "probe-count",
+        severity: Severity::Error,
+        description: "there are as many probe x locations as z locations",
+        check: probe_count,
+    },
+    Rule {
+        id: "probe-inside-grid",
+        severity: Severity::Error,
+        description: "every probe location is an index inside the grid",
+        check: probe_inside_grid,
+    },
+    Rule {
+        id: "blowing-slot",
+        severity: Severity::Error,
+        description: "the blowing slot starts before it ends, and ends inside the grid",
+        check: blowing_slot,
+    },
+    Rule {
+        id: "shock-impingement",
+        severity: Severity::Error,
+        description: "the shock strikes the bottom surface inside the domain",
+        check: shock_impingement,
+    },
+    Rule {
+        id: "shock-attached",
+        severity: Severity::Error,
+        description: "the shock angle gives an attached oblique shock at the Mach number",
+        check: shock_attached,
+    },
+    Rule {
+        id: "python-flowfield-steps",
+        severity: Severity::Warning,
+        description: "python-flowfield-steps is only set for the python solver",
+        check: python_flowfield_steps,
+    },
+    Rule {
+        id: "io-steps",
+        severity: Severity::Warning,
+        description: "output intervals are no more than the number of steps",
+        check: io_steps,
+    },
+];
+
+/// check a config against every rule, returning every violation
+pub(crate) fn check(config: &Config, gpu_memory: Option<Megabytes>) -> Vec<Violation> {
+    RULES
+        .iter()
+        .flat_map(|rule| {
+            (rule.check)(config, gpu_memory)
+                .into_iter()
+                .map(|message| Violation {
+                    rule: rule.id,
+                    severity: rule.severity,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// one violation per line
+pub(crate) fn format_violations(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|violation| format!("    {violation}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn validate(args: cli::Validate) -> anyhow::Result<()> {
+    if args.list_rules {
+        for rule in RULES {
+            // the derived display of the severity does not pad itself
+            let severity = rule.severity.to_string();
+            println!("{:<26} {severity:<8} {}", rule.id, rule.description);
+        }
+        return Ok(());
+    }
+
+    // clap requires a config unless the rules are listed
+    let path = args.config.unwrap();
+    let config = Config::load(&path, &args.overrides)
+        .with_context(|| format!("failed to read config at {}", path.display()))?;
+
+    let gpu_memory = crate::gpu::selected_gpu_memory(&args.gpu)?;
+
+    let violations = check(&config, gpu_memory);
+
+    let errors = violations
+        .iter()
+        .filter(|violation| violation.severity == Severity::Error)
+        .count();
+    let warnings = violations.len() - errors;
+
+    println!(
+        "checked {} against {} rules: {errors} error(s), {warnings} warning(s)",
+        path.display(),
+        RULES.len()
+    );
+
+    if !violations.is_empty() {
+        println!("{}", format_violations(&violations));
+    }
+
+    if errors > 0 || (args.deny_warnings && warnings > 0) {
+        anyhow::bail!("{} is not a valid config", path.display());
+    }
+
+    Ok(())
+}
+
+fn mpi_split_positive(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    [
+        ("mpi-x-split", config.mpi_x_split),
+        ("mpi-z-split", config.mpi_z_split),
+    ]
+    .into_iter()
+    .filter(|(_, split)| *split == 0)
+    .map(|(name, _)| format!("{name} must be at least 1"))
+    .collect()
+}
+
+/// a message if `divisions` cannot be split evenly between `split` ranks. A split of 0 is left
+/// to `mpi-split-positive`
+fn uneven_split(axis: &str, divisions: usize, name: &str, split: usize) -> Vec<String> {
+    if split == 0 || divisions % split == 0 {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "{axis}-divisions ({divisions}) cannot be evenly split between {name} ({split}) ranks (remainder: {})",
+        divisions % split
+    )]
+}
+
+fn mpi_split_x(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    let mut messages = uneven_split("x", config.x_divisions, "mpi-x-split", config.mpi_x_split);
+    if !messages.is_empty() {
+        messages[0].push_str(". Run `decompose` to list the valid splits of this grid");
+    }
+    messages
+}
+
+fn mpi_split_y(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    uneven_split("y", config.y_divisions, "mpi-x-split", config.mpi_x_split)
+}
+
+fn mpi_split_z(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    uneven_split("z", config.z_divisions, "mpi-z-split", config.mpi_z_split)
+}
+
+/// replication of the memory checking code in fortran, which is run on every mpi rank for its
+/// own part of the domain
+fn gpu_memory(config: &Config, gpu_memory: Option<Megabytes>) -> Vec<String> {
+    let Some(gpu_memory) = gpu_memory else {
+        return Vec::new();
+    };
+
+    let required = config.gpu_memory_per_rank();
+
+    if required <= gpu_memory {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "gpu memory capacity: {gpu_memory}, memory required on each of the {} gpus (one per mpi rank): {required}",
+        config.nproc()
+    )]
+}
+
+fn wall_resolved_points(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    if config.y_divisions > config.nymax_wr {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "y-divisions ({}) must be greater than nymax-wr ({})",
+        config.y_divisions, config.nymax_wr
+    )]
+}
+
+fn wall_resolved_length(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    if config.y_length > config.rly_wr {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "y-length ({}) must be greater than rly-wr ({})",
+        config.y_length, config.rly_wr
+    )]
+}
+
+fn wall_resolved_stretching(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    match ExpectedMesh::from_config(config) {
+        // the size of the wall resolved region is checked by other rules
+        Ok(_)
+        | Err(MeshError::ChannelFlow)
+        | Err(MeshError::WallResolvedPoints { .. })
+        | Err(MeshError::WallResolvedLength { .. }) => Vec::new(),
+        Err(error) => vec![error.to_string()],
+    }
+}
+
+fn probe_count(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    if config.probe_locations_x.len() == config.probe_locations_z.len() {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "there were {} probe x locations (--probe-locations-x) and {} probe z locations (--probe-locations-z)",
+        config.probe_locations_x.len(),
+        config.probe_locations_z.len(),
+    )]
+}
+
+fn probe_inside_grid(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    config
+        .probe_locations_x
+        .iter()
+        .zip(&config.probe_locations_z)
+        // probe locations are 1 indexed, as in the solver
+        .filter(|(x, z)| {
+            !(1..=config.x_divisions).contains(*x) || !(1..=config.z_divisions).contains(*z)
+        })
+        .map(|(x, z)| {
+            format!(
+                "probe location (x: {x}, z: {z}) is outside of the grid, probe locations are 1 indexed so x indices must be from 1 to x-divisions ({}) and z indices from 1 to z-divisions ({})",
+                config.x_divisions, config.z_divisions
+            )
+        })
+        .collect()
+}
+
+fn blowing_slot(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    let Some((slot_start, slot_end)) = config.blowing_bc.slot() else {
+        return Vec::new();
+    };
+
+    if slot_start < slot_end && slot_end < config.x_divisions {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "the blowing slot (slot-start: {slot_start}, slot-end: {slot_end}) must have slot-start < slot-end < x-divisions ({})",
+        config.x_divisions
+    )]
+}
+
+fn shock_impingement(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    if !matches!(config.flow_type, FlowType::ShockBoundaryLayer)
+        || (config.shock_impingement > 0. && config.shock_impingement < config.x_length)
+    {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "shock-impingement ({}) must be inside the domain, between 0 and x-length ({})",
+        config.shock_impingement, config.x_length
+    )]
+}
+
+fn shock_attached(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    let max_angle = max_deflection_angle(config.mach_number);
+
+    if !matches!(config.flow_type, FlowType::ShockBoundaryLayer)
+        || (config.shock_angle > 0. && config.shock_angle < max_angle)
+    {
+        return Vec::new();
+    }
+
+    vec![format!(
+        "shock-angle ({}) must be between 0 and {max_angle:.2} degrees for an attached oblique shock at Mach {}. Decrease the shock angle or increase the Mach number",
+        config.shock_angle, config.mach_number
+    )]
+}
+
+fn python_flowfield_steps(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    if config.python_flowfield_steps.is_none() || config.use_python {
+        return Vec::new();
+    }
+
+    vec![
+        "python-flowfield-steps is only used by the python solver, pass --use-python or remove it"
+            .into(),
+    ]
+}
+
+fn io_steps(config: &Config, _: Option<Megabytes>) -> Vec<String> {
+    [
+        ("probe-io-steps", config.probe_io_steps),
+        ("span-average-io-steps", config.span_average_io_steps),
+        (
+            "python-flowfield-steps",
+            config.python_flowfield_steps.unwrap_or(0),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, io_steps)| *io_steps > config.steps)
+    .map(|(name, io_steps)| {
+        format!(
+            "{name} ({io_steps}) is more than the number of steps ({}), this output will never be written",
+            config.steps
+        )
+    })
+    .collect()
+}
+
+/// the largest flow deflection (degrees) with an attached oblique shock at a given Mach number
+///
+/// found by maximizing the deflection of the theta-beta-Mach relation over every shock angle
+/// between the Mach angle and a normal shock
+fn max_deflection_angle(mach: f64) -> f64 {
+    const GAMMA: f64 = 1.4;
+    const SAMPLES: usize = 10_000;
+
+    if mach <= 1. {
+        return 0.;
+    }
+
+    let mach_angle = (1. / mach).asin();
+    let normal_shock = std::f64::consts::FRAC_PI_2;
+
+    (0..=SAMPLES)
+        .map(|i| mach_angle + (normal_shock - mach_angle) * i as f64 / SAMPLES as f64)
+        .map(|beta| {
+            let numerator = 2. / beta.tan() * (mach.powi(2) * beta.sin().powi(2) - 1.);
+            let denominator = mach.powi(2) * (GAMMA + (2. * beta).cos()) + 2.;
+            (numerator / denominator).atan().to_degrees()
+        })
+        .fold(0., f64::max)
+}
+
+#[cfg(test)]
+fn default_config() -> Config {
+    cli::ConfigGenerator::with_path(PathBuf::new()).into_serializable()
+}
+
+#[cfg(test)]
+/// ids of the rules a config violates
+fn violated(config: &Config, gpu_memory: Option<Megabytes>) -> Vec<&'static str> {
+    check(config, gpu_memory)
+        .iter()
+        .map(|violation| violation.rule)
+        .collect()
+}
+
+#[test]
+fn rule_ids_are_unique() {
+    let mut ids = RULES.iter().map(|rule| rule.id).collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), RULES.len());
+}
+
+#[test]
+fn default_config_is_valid() {
+    assert!(violated(&default_config(), Some(Megabytes(11_000))).is_empty());
+}
+
+#[test]
+fn every_violation_is_reported() {
+    let mut config = default_config();
+    config.x_divisions = 801;
+    config.z_divisions = 151;
+    config.mpi_z_split = 2;
+    config.nymax_wr = 300;
+
+    assert_eq!(
+        violated(&config, None),
+        ["mpi-split-x", "mpi-split-z", "wall-resolved-points"]
+    );
+}
+
+#[test]
+fn mpi_splits() {
+    let mut config = default_config();
+    config.mpi_x_split = 0;
+    assert_eq!(violated(&config, None), ["mpi-split-positive"]);
+
+    config.mpi_x_split = 16;
+    config.y_divisions = 216;
+    assert_eq!(violated(&config, None), ["mpi-split-y"]);
+}
+
+#[test]
+fn gpu_memory_is_only_checked_with_a_gpu() {
+    let config = default_config();
+    assert!(violated(&config, None).is_empty());
+    assert_eq!(violated(&config, Some(Megabytes(10))), ["gpu-memory"]);
+}
+
+#[test]
+fn wall_resolved_region() {
+    let mut config = default_config();
+    config.rly_wr = 7.;
+    assert_eq!(violated(&config, None), ["wall-resolved-length"]);
+
+    // 0.5 / 200 is 0.625 wall units at the default Reynolds number
+    config.rly_wr = 0.5;
+    assert_eq!(violated(&config, None), ["wall-resolved-stretching"]);
+    assert_eq!(check(&config, None)[0].severity, Severity::Warning);
+}
+
+#[test]
+fn probes() {
+    let mut config = default_config();
+    // the last point of the grid is a valid location
+    config.probe_locations_x = vec![1, config.x_divisions];
+    config.probe_locations_z = vec![1, config.z_divisions];
+    assert!(violated(&config, None).is_empty());
+
+    for (x, z) in [
+        (0, 10),
+        (10, 0),
+        (config.x_divisions + 1, 10),
+        (10, config.z_divisions + 1),
+    ] {
+        config.probe_locations_x = vec![10, x];
+        config.probe_locations_z = vec![10, z];
+        assert_eq!(violated(&config, None), ["probe-inside-grid"], "({x}, {z})");
+    }
+
+    config.probe_locations_x = vec![10, 20];
+    config.probe_locations_z.push(10);
+    assert_eq!(violated(&config, None), ["probe-count"]);
+}
+
+#[test]
+fn blowing_slot_is_ordered() {
+    let mut config = default_config();
+    config.blowing_bc = cli::JetActuator::Constant {
+        amplitude: 1.,
+        slot_start: 200,
+        slot_end: 100,
+    };
+    assert_eq!(violated(&config, None), ["blowing-slot"]);
+}
+
+#[test]
+fn shock() {
+    // the largest deflection for an attached shock at Mach 2 is just under 23 degrees
+    let max_angle = max_deflection_angle(2.);
+    assert!(max_angle > 22.9 && max_angle < 23.1);
+
+    let mut config = default_config();
+    config.mach_number = 1.2;
+    config.shock_impingement = 30.;
+    assert_eq!(
+        violated(&config, None),
+        ["shock-impingement", "shock-attached"]
+    );
+
+    // shocks are only placed in the shock boundary layer case
+    config.flow_type = FlowType::BoundaryLayer;
+    assert!(violated(&config, None).is_empty());
+}
+
+#[test]
+fn unreachable_output_is_a_warning() {
+    let mut config = default_config();
+    config.steps = 50;
+    config.python_flowfield_steps = Some(10);
+
+    // span averages every 100 steps, and python flowfields without the python solver
+    assert_eq!(
+        violated(&config, None),
+        ["python-flowfield-steps", "io-steps"]
+    );
+    assert_eq!(config.validate(None).unwrap().len(), 2);
+}
@@ -1,51 +1,532 @@
+//! export span averages (from .vtr files or a `span_averages.h5` file) to a single file of arrays
+//!
+//! the exported file contains
+//!
+//! | Variable        | Shape              | Data |
+//! |-----------------|--------------------|------|
+//! | span_averages   | `(n, 4, nx, ny)`   | rho, u, v, w |
+//! | energy          | `(n, nx, ny)`      | total energy per unit mass |
+//! | pressure        | `(n, nx, ny)`      | only with `--derived` |
+//! | temperature     | `(n, nx, ny)`      | only with `--derived` |
+//! | x, y            | `(nx)`, `(ny)`     | mesh coordinates (empty if the mesh could not be read) |
+//! | steps           | `(n)`              | solver step of each span average |
+//! | config          | string             | the input.json of the run |
+//!
+//! MATLAB v5 files and NumPy arrays cannot hold strings, so `config` is stored as its character
+//! codes there (`char(config)` recovers the json). Without `--derived`, pressure and temperature
+//! are empty arrays in MATLAB v5 files and left out of every other format.
+
+use crate::binary_to_vtk::SpanVtkInformation;
+use crate::mat73;
+use crate::npy;
 use crate::prelude::*;
-use ndarray::s;
+use crate::snapshots;
+use crate::snapshots::{SNAPSHOT_FIELDS, SPAN_HDF5_FIELDS};
+
+use ndarray::{s, Array1};
+use rayon::prelude::*;
+
+/// ratio of specific heats of the (ideal) gas
+const GAMMA: f64 = 1.4;
+
+pub(crate) fn vtk_to_mat(mut args: cli::VtkToMat) -> anyhow::Result<()> {
+    let config = Config::load(&args.config, &args.overrides)?;
+
+    let is_hdf5 = |path: &PathBuf| path.extension().map(|ext| ext == "h5").unwrap_or(false);
+
+    let source = match args.input_files.as_slice() {
+        // a span averages file written by `spans-to-hdf5`
+        [path] if is_hdf5(path) => Source::open_hdf5(path)?,
+        _ => {
+            // sort all the input files
+            args.input_files.sort_unstable();
+            Source::Vtk(args.input_files.clone())
+        }
+    };
+
+    let mesh_directory = args
+        .mesh
+        .clone()
+        .or_else(|| args.config.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let mesh = match run::MeshInfo::from_base_path(&mesh_directory, &config) {
+        Ok(mesh) => Some(mesh),
+        Err(e) => {
+            println!(
+                "could not read mesh information from {} ({e}), x and y will be empty",
+                mesh_directory.display()
+            );
+            None
+        }
+    };
+
+    let metadata = Metadata {
+        x: mesh
+            .as_ref()
+            .map(|mesh| mesh.x_data.clone())
+            .unwrap_or_default(),
+        y: mesh
+            .as_ref()
+            .map(|mesh| mesh.y_data.clone())
+            .unwrap_or_default(),
+        steps: source.steps()?,
+        config: serde_json::to_string(&config)?,
+    };
+
+    let shape = (config.x_divisions, config.y_divisions);
+    let mut sink = Sink::create(&args, metadata.steps.len(), shape)?;
+
+    source.for_each_snapshot(metadata.steps.len(), shape, |idx, snapshot| {
+        sink.write(idx, snapshot)
+    })?;
+
+    sink.finish(&args, metadata)
+}
+
+/// one span average, along with the energy that is not part of the snapshot fields
+struct Snapshot {
+    /// `(4, nx, ny)` array of rho, u, v, w
+    fields: Array3,
+    energy: Array2,
+}
+
+impl Snapshot {
+    /// the fields of a .vtr file at `path`, which must be on a `(nx, ny)` grid
+    fn from_vtk(
+        information: SpanVtkInformation,
+        shape: (usize, usize),
+        path: &Path,
+    ) -> anyhow::Result<Self> {
+        let (nx, ny) = shape;
+
+        let rho: Array2 = information.rho.into();
+        let velocity: Array3 = information.velocity.into();
+        let energy: Array2 = information.energy.into();
+
+        if rho.dim() != shape || energy.dim() != shape || velocity.dim() != (3, nx, ny) {
+            anyhow::bail!(
+                "{} is a {} x {} grid, but the config has {nx} x {ny} points",
+                path.display(),
+                rho.dim().0,
+                rho.dim().1,
+            );
+        }
+
+        let mut fields = Array3::zeros((SNAPSHOT_FIELDS, nx, ny));
+        fields.slice_mut(s![0usize, .., ..]).assign(&rho);
+        fields.slice_mut(s![1usize..4, .., ..]).assign(&velocity);
+
+        Ok(Self { fields, energy })
+    }
+
+    /// pressure of an ideal gas, `p = (gamma - 1) * rho * (E - |u|^2 / 2)`
+    fn pressure(&self) -> Array2 {
+        let rho = self.fields.slice(s![0usize, .., ..]);
+        let u = self.fields.slice(s![1usize, .., ..]);
+        let v = self.fields.slice(s![2usize, .., ..]);
+        let w = self.fields.slice(s![3usize, .., ..]);
 
-pub(crate) fn vtk_to_mat(mut args: cli::VtkToMat) -> Result<(), Error> {
-    let config = Config::from_path(&args.config)?;
+        let mut pressure = Array2::zeros(self.energy.dim());
+        ndarray::Zip::from(&mut pressure)
+            .and(&rho)
+            .and(&u)
+            .and(&v)
+            .and(&w)
+            .and(&self.energy)
+            .for_each(|p, rho, u, v, w, energy| {
+                let kinetic = 0.5 * (u * u + v * v + w * w);
+                *p = (GAMMA - 1.) * rho * (energy - kinetic);
+            });
 
-    // sort all the input files
-    args.input_files.sort_unstable();
+        pressure
+    }
 
-    let num_files = args.input_files.len();
+    /// nondimensional temperature of an ideal gas, `T = p / rho`
+    fn temperature(&self, pressure: &Array2) -> Array2 {
+        pressure / &self.fields.slice(s![0usize, .., ..])
+    }
+}
 
-    let mut span_averages = Array4::zeros((num_files, 4, config.x_divisions, config.y_divisions));
+/// where the span averages are read from
+enum Source {
+    /// sorted .vtr files
+    Vtk(Vec<PathBuf>),
+    /// a span averages file written by `spans-to-hdf5`
+    Hdf5 { path: PathBuf, file: hdf5::File },
+}
 
-    for (idx, file) in args.input_files.into_iter().enumerate() {
-        println!("reading {}", file.display());
-        let vtk_data: vtk::VtkData<
-            vtk::Rectilinear2D<f64, vtk::Binary>,
-            crate::binary_to_vtk::SpanVtkInformation,
-        > = vtk::read_vtk(&file)?;
+impl Source {
+    fn open_hdf5(path: &Path) -> anyhow::Result<Self> {
+        let file = hdf5::File::open(path)
+            .with_context(|| format!("failed to open span averages file {}", path.display()))?;
 
-        // unpack data from the arrays
-        let rho: ndarray::Array2<f64> = vtk_data.data.rho.into();
-        let velocity: ndarray::Array3<f64> = vtk_data.data.velocity.into();
-        let u = velocity.slice(s!(0usize, .., ..));
-        let v = velocity.slice(s!(1usize, .., ..));
-        let w = velocity.slice(s!(2usize, .., ..));
+        Ok(Self::Hdf5 {
+            path: path.to_owned(),
+            file,
+        })
+    }
 
-        // store that new data in the array at the appropriate time step
-        span_averages
-            .slice_mut(s![idx, 0usize, .., ..])
-            .assign(&rho);
-        span_averages.slice_mut(s![idx, 1usize, .., ..]).assign(&u);
-        span_averages.slice_mut(s![idx, 2usize, .., ..]).assign(&v);
-        span_averages.slice_mut(s![idx, 3usize, .., ..]).assign(&w);
+    /// the solver step of every span average
+    fn steps(&self) -> anyhow::Result<Vec<usize>> {
+        match self {
+            Self::Vtk(files) => Ok(files
+                .iter()
+                .enumerate()
+                .map(|(idx, file)| snapshots::step_number(file).unwrap_or(idx))
+                .collect()),
+            Self::Hdf5 { path, file } => Ok(file
+                .dataset("step")
+                .and_then(|dataset| dataset.read_1d::<u64>())
+                .with_context(|| format!("failed to read steps from {}", path.display()))?
+                .iter()
+                .map(|step| *step as usize)
+                .collect()),
+        }
     }
 
-    let spans = SpanAverages::new(span_averages);
+    /// read every one of the `num_snapshots` snapshots in order, passing each to `f` along with
+    /// its index. Every snapshot must be on a `(nx, ny)` grid
+    fn for_each_snapshot<F>(
+        &self,
+        num_snapshots: usize,
+        shape: (usize, usize),
+        mut f: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnMut(usize, Snapshot) -> anyhow::Result<()>,
+    {
+        match self {
+            Self::Vtk(files) => {
+                for (batch_idx, batch) in files.chunks(utils::BATCH_SIZE).enumerate() {
+                    let spans = batch
+                        .par_iter()
+                        .map(|path| {
+                            snapshots::read_span_vtk(path)
+                                .with_context(|| format!("failed to read {}", path.display()))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    for (offset, (information, path)) in spans.into_iter().zip(batch).enumerate() {
+                        f(
+                            batch_idx * utils::BATCH_SIZE + offset,
+                            Snapshot::from_vtk(information, shape, path)?,
+                        )?;
+                    }
+
+                    println!(
+                        "read {}/{} span averages",
+                        batch_idx * utils::BATCH_SIZE + batch.len(),
+                        files.len()
+                    );
+                }
+            }
+            Self::Hdf5 { path, file } => {
+                let datasets = SPAN_HDF5_FIELDS
+                    .iter()
+                    .map(|name| {
+                        file.dataset(name).with_context(|| {
+                            format!("dataset `{name}` was missing from {}", path.display())
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let expected = [num_snapshots, shape.0, shape.1];
 
-    let writer = std::io::BufWriter::new(
-        std::fs::File::create(&args.output_file)
-            .map_err(|e| FileError::new(args.output_file, e))?,
-    );
-    mat5::MatFile::write_contents(&spans, writer)?;
+                for (dataset, name) in datasets.iter().zip(SPAN_HDF5_FIELDS) {
+                    if dataset.shape() != expected {
+                        anyhow::bail!(
+                            "dataset `{name}` in {} has shape {:?}, but {} steps on the {} x {} grid of the config need {expected:?}",
+                            path.display(),
+                            dataset.shape(),
+                            num_snapshots,
+                            shape.0,
+                            shape.1,
+                        );
+                    }
+                }
 
-    Ok(())
+                for idx in 0..num_snapshots {
+                    let context =
+                        || format!("failed to read span average {idx} of {}", path.display());
+
+                    let mut fields = Array3::zeros((SNAPSHOT_FIELDS, shape.0, shape.1));
+
+                    for (field_idx, dataset) in datasets.iter().take(SNAPSHOT_FIELDS).enumerate() {
+                        let field: Array2 =
+                            dataset.read_slice(s![idx, .., ..]).with_context(context)?;
+                        fields.slice_mut(s![field_idx, .., ..]).assign(&field);
+                    }
+
+                    let energy = datasets[SNAPSHOT_FIELDS]
+                        .read_slice(s![idx, .., ..])
+                        .with_context(context)?;
+
+                    f(idx, Snapshot { fields, energy })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// everything in the output other than the snapshots themselves
+struct Metadata {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    steps: Vec<usize>,
+    config: String,
 }
 
-#[derive(Debug, Constructor, mat5::MatFile)]
+impl Metadata {
+    fn steps(&self) -> Vec<f64> {
+        self.steps.iter().map(|step| *step as f64).collect()
+    }
+
+    /// the character codes of the config, for formats that cannot store strings
+    fn config_chars(&self) -> Vec<f64> {
+        self.config.chars().map(|c| c as u32 as f64).collect()
+    }
+}
+
+/// where the snapshots are written to
+enum Sink {
+    /// formats that must be written all at once
+    InMemory {
+        span_averages: Array4,
+        energy: Array3,
+        derived: Option<(Array3, Array3)>,
+    },
+    /// hdf5 based formats that snapshots are streamed to, so that only a batch of snapshots is
+    /// ever in memory
+    Streamed {
+        writer: mat73::Hdf5ArrayWriter,
+        span_averages: mat73::SnapshotDataset,
+        energy: mat73::SnapshotDataset,
+        derived: Option<(mat73::SnapshotDataset, mat73::SnapshotDataset)>,
+    },
+}
+
+impl Sink {
+    fn create(
+        args: &cli::VtkToMat,
+        num_snapshots: usize,
+        shape: (usize, usize),
+    ) -> anyhow::Result<Self> {
+        let (nx, ny) = shape;
+
+        let matlab = match args.format {
+            cli::ArrayFormat::Mat73 => true,
+            cli::ArrayFormat::Hdf5 => false,
+            cli::ArrayFormat::Mat | cli::ArrayFormat::Npy | cli::ArrayFormat::Npz => {
+                let field = || Array3::zeros((num_snapshots, nx, ny));
+
+                return Ok(Self::InMemory {
+                    span_averages: Array4::zeros((num_snapshots, SNAPSHOT_FIELDS, nx, ny)),
+                    energy: field(),
+                    derived: args.derived.then(|| (field(), field())),
+                });
+            }
+        };
+
+        let writer = mat73::Hdf5ArrayWriter::create(&args.output_file, matlab)?;
+        let span_averages =
+            writer.snapshot_array("span_averages", num_snapshots, &[SNAPSHOT_FIELDS, nx, ny])?;
+        let energy = writer.snapshot_array("energy", num_snapshots, &[nx, ny])?;
+        let derived = if args.derived {
+            Some((
+                writer.snapshot_array("pressure", num_snapshots, &[nx, ny])?,
+                writer.snapshot_array("temperature", num_snapshots, &[nx, ny])?,
+            ))
+        } else {
+            None
+        };
+
+        Ok(Self::Streamed {
+            writer,
+            span_averages,
+            energy,
+            derived,
+        })
+    }
+
+    fn write(&mut self, idx: usize, snapshot: Snapshot) -> anyhow::Result<()> {
+        match self {
+            Self::InMemory {
+                span_averages,
+                energy,
+                derived,
+            } => {
+                if let Some((pressure, temperature)) = derived {
+                    let snapshot_pressure = snapshot.pressure();
+                    temperature
+                        .slice_mut(s![idx, .., ..])
+                        .assign(&snapshot.temperature(&snapshot_pressure));
+                    pressure
+                        .slice_mut(s![idx, .., ..])
+                        .assign(&snapshot_pressure);
+                }
+
+                span_averages
+                    .slice_mut(s![idx, .., .., ..])
+                    .assign(&snapshot.fields);
+                energy.slice_mut(s![idx, .., ..]).assign(&snapshot.energy);
+            }
+            Self::Streamed {
+                span_averages,
+                energy,
+                derived,
+                ..
+            } => {
+                if let Some((pressure, temperature)) = derived {
+                    let snapshot_pressure = snapshot.pressure();
+                    temperature.write(idx, snapshot.temperature(&snapshot_pressure).view())?;
+                    pressure.write(idx, snapshot_pressure.view())?;
+                }
+
+                span_averages.write(idx, snapshot.fields.view())?;
+                energy.write(idx, snapshot.energy.view())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self, args: &cli::VtkToMat, metadata: Metadata) -> anyhow::Result<()> {
+        let path = &args.output_file;
+
+        match self {
+            Self::InMemory {
+                span_averages,
+                energy,
+                derived,
+            } => match args.format {
+                cli::ArrayFormat::Mat => {
+                    let (_, nx, ny) = energy.dim();
+                    let (pressure, temperature) = derived.unwrap_or_else(|| {
+                        (Array3::zeros((0, nx, ny)), Array3::zeros((0, nx, ny)))
+                    });
+
+                    let output = SpanAverages {
+                        span_averages,
+                        energy,
+                        pressure,
+                        temperature,
+                        steps: metadata.steps(),
+                        config: metadata.config_chars(),
+                        x: metadata.x,
+                        y: metadata.y,
+                    };
+
+                    let writer = io::BufWriter::new(
+                        fs::File::create(path)
+                            .with_context(|| format!("failed to create {}", path.display()))?,
+                    );
+                    mat5::MatFile::write_contents(&output, writer)?;
+                }
+                cli::ArrayFormat::Npy | cli::ArrayFormat::Npz => {
+                    let mut arrays = vec![("energy", energy.into_dyn())];
+                    if let Some((pressure, temperature)) = derived {
+                        arrays.push(("pressure", pressure.into_dyn()));
+                        arrays.push(("temperature", temperature.into_dyn()));
+                    }
+                    arrays.extend([
+                        ("x", Array1::from(metadata.x.clone()).into_dyn()),
+                        ("y", Array1::from(metadata.y.clone()).into_dyn()),
+                        ("steps", Array1::from(metadata.steps()).into_dyn()),
+                        ("config", Array1::from(metadata.config_chars()).into_dyn()),
+                    ]);
+
+                    if let cli::ArrayFormat::Npz = args.format {
+                        let mut npz = npy::NpzWriter::create(path)?;
+                        npz.add_array("span_averages", &span_averages)?;
+                        for (name, array) in &arrays {
+                            npz.add_array(name, array)?;
+                        }
+                        npz.finish()?;
+                    } else {
+                        // span_averages is written to the output file, and every other array
+                        // to a file next to it
+                        npy::write_npy_file(path, &span_averages)?;
+
+                        let stem = path
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                            .unwrap_or_default();
+
+                        for (name, array) in &arrays {
+                            let sibling = path.with_file_name(format!("{stem}_{name}.npy"));
+                            npy::write_npy_file(&sibling, array)?;
+                        }
+                    }
+                }
+                cli::ArrayFormat::Mat73 | cli::ArrayFormat::Hdf5 => {
+                    unreachable!("hdf5 based formats are always streamed")
+                }
+            },
+            Self::Streamed { writer, .. } => {
+                writer.write_array("x", ndarray::ArrayView1::from(metadata.x.as_slice()))?;
+                writer.write_array("y", ndarray::ArrayView1::from(metadata.y.as_slice()))?;
+                writer.write_array("steps", Array1::from(metadata.steps()).view())?;
+                writer.write_string("config", &metadata.config)?;
+                writer.finish()?;
+            }
+        }
+
+        println!("span averages written to {}", path.display());
+
+        Ok(())
+    }
+}
+
+/// see the module documentation for the contents of each field
+#[derive(Debug, mat5::MatFile)]
 struct SpanAverages {
     span_averages: Array4,
+    energy: Array3,
+    pressure: Array3,
+    temperature: Array3,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    steps: Vec<f64>,
+    config: Vec<f64>,
+}
+
+#[test]
+fn pressure_and_temperature_of_ideal_gas() {
+    let mut fields = Array3::zeros((SNAPSHOT_FIELDS, 1, 1));
+    // rho, u, v, w
+    fields[(0, 0, 0)] = 2.0;
+    fields[(1, 0, 0)] = 1.0;
+    fields[(2, 0, 0)] = 2.0;
+    fields[(3, 0, 0)] = 2.0;
+
+    let snapshot = Snapshot {
+        fields,
+        energy: Array2::from_elem((1, 1), 10.0),
+    };
+
+    // the kinetic energy is 4.5, so p = 0.4 * 2 * 5.5
+    let pressure = snapshot.pressure();
+    assert!((pressure[(0, 0)] - 4.4).abs() < 1e-12);
+
+    let temperature = snapshot.temperature(&pressure);
+    assert!((temperature[(0, 0)] - 2.2).abs() < 1e-12);
+}
+
+#[test]
+fn snapshot_on_another_grid_is_an_error() {
+    let information = |nx: usize, ny: usize| SpanVtkInformation {
+        rho: vtk::Scalar2D::new(Array2::zeros((nx, ny))),
+        velocity: vtk::Vector2D::new(Array3::zeros((3, nx, ny))),
+        energy: vtk::Scalar2D::new(Array2::zeros((nx, ny))),
+    };
+    let path = Path::new("span_average_00010_average.vtr");
+
+    assert!(Snapshot::from_vtk(information(4, 3), (4, 3), path).is_ok());
+
+    let error = Snapshot::from_vtk(information(5, 3), (4, 3), path)
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("span_average_00010"));
 }